@@ -0,0 +1,159 @@
+//! `gate0-dsl check`: parses and validates `.gate0` files and the `policy_builder!`
+//! and `policy_fn!` bodies in `.rs` files, like the macros would at compile time.
+//!
+//! Exits with 0 when nothing was reported, 1 when there were only warnings and 2 on
+//! errors, including files that couldn't be read.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use gate0_dsl_core::source::find_invocations;
+use gate0_dsl_core::validate::{Severity, SourceDiagnostic, validate_fn_source, validate_source};
+use proc_macro2::TokenStream;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Human,
+    Json,
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> ExitCode {
+    let crate::Args {
+        options,
+        positional: paths,
+    } = match crate::parse_args(args, &["format"]) {
+        Ok(args) => args,
+        Err(message) => return crate::usage_error(&message),
+    };
+    let mut format = Format::Human;
+    for (_, value) in options {
+        format = match value.as_str() {
+            "human" => Format::Human,
+            "json" => Format::Json,
+            _ => return crate::usage_error(&format!("unknown format `{value}`")),
+        };
+    }
+    let mut paths = paths.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let mut files = Vec::new();
+    let mut failed = false;
+    for path in &paths {
+        if let Err(err) = crate::collect_files(path, &mut files) {
+            eprintln!("{}: {err}", path.display());
+            failed = true;
+        }
+    }
+
+    let (mut errors, mut warnings) = (0, 0);
+    for file in &files {
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{}: {err}", file.display());
+                failed = true;
+                continue;
+            }
+        };
+        for diagnostic in check_file(file, &source) {
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+            print(format, file, &diagnostic);
+        }
+    }
+
+    if format == Format::Human && errors + warnings > 0 {
+        eprintln!(
+            "{errors} error{}, {warnings} warning{}",
+            if errors == 1 { "" } else { "s" },
+            if warnings == 1 { "" } else { "s" }
+        );
+    }
+
+    if failed || errors > 0 {
+        ExitCode::from(2)
+    } else if warnings > 0 {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Checks a `.gate0` file as a whole, or each `policy_builder!` and `policy_fn!` body in
+/// a `.rs` file. Bodies declaring `macro_rules!` metavariables outside of `macro_rules!`
+/// definitions are reported as not checked.
+fn check_file(path: &Path, source: &str) -> Vec<SourceDiagnostic> {
+    if path.extension().is_none_or(|ext| ext != "rs") {
+        return validate_source(source);
+    }
+
+    let tokens = match TokenStream::from_str(source) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            let start = err.span().start();
+            return vec![SourceDiagnostic {
+                severity: Severity::Error,
+                line: start.line,
+                column: start.column + 1,
+                message: err.to_string(),
+            }];
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    for invocation in find_invocations(tokens, &["policy_builder", "policy_fn"]) {
+        if invocation.template {
+            let start = invocation.name.span().start();
+            diagnostics.push(SourceDiagnostic {
+                severity: Severity::Warning,
+                line: start.line,
+                column: start.column + 1,
+                message: format!(
+                    "`{}!` isn't checked, as its body declares `macro_rules!` metavariables",
+                    invocation.name
+                ),
+            });
+            continue;
+        }
+
+        // `policy_fn!` bodies are checked as a whole, signature and companion lines
+        // included, like the macro does.
+        let validate = if invocation.name == "policy_fn" {
+            validate_fn_source
+        } else {
+            validate_source
+        };
+        let open = invocation.body.span_open();
+        let body = open.byte_range().end..invocation.body.span_close().byte_range().start;
+        let start = open.end();
+        for mut diagnostic in validate(&source[body]) {
+            if diagnostic.line == 1 {
+                diagnostic.column += start.column;
+            }
+            diagnostic.line += start.line - 1;
+            diagnostics.push(diagnostic);
+        }
+    }
+    diagnostics
+}
+
+fn print(format: Format, path: &Path, diagnostic: &SourceDiagnostic) {
+    match format {
+        Format::Human => println!("{}:{diagnostic}", path.display()),
+        Format::Json => println!(
+            "{}",
+            serde_json::json!({
+                "path": path.display().to_string(),
+                "line": diagnostic.line,
+                "column": diagnostic.column,
+                "severity": diagnostic.severity.to_string(),
+                "message": diagnostic.message,
+            })
+        ),
+    }
+}
@@ -0,0 +1,511 @@
+//! `gate0-dsl eval`: evaluates requests against a `.gate0` policy and reports the
+//! decision with the rule that made it.
+//!
+//! Reason code identifiers are resolved through `--reason NAME=CODE`. Requests come
+//! from the command line or, one JSON object per line, from `--requests`:
+//!
+//! ```json
+//! {"principal": "alice", "action": "read", "resource": "doc", "context": {"role": "admin"}}
+//! ```
+//!
+//! Exits with 0 when every request was evaluated and 2 otherwise.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use gate0::{Effect, ReasonCode};
+use gate0_dsl_core::ast::{PolicyDefinition, RuleDefinition};
+use gate0_dsl_core::runtime::{ParseError, build_config, build_rule, parse_policy_file};
+use proc_macro2::{TokenStream, TokenTree};
+use serde_json::json;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Human,
+    Json,
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> ExitCode {
+    let names = [
+        "principal",
+        "action",
+        "resource",
+        "ctx",
+        "reason",
+        "requests",
+        "format",
+    ];
+    let crate::Args {
+        options,
+        positional,
+    } = match crate::parse_args(args, &names) {
+        Ok(args) => args,
+        Err(message) => return crate::usage_error(&message),
+    };
+    let [path] = positional.as_slice() else {
+        return crate::usage_error("expected exactly one policy file");
+    };
+
+    let mut format = Format::Human;
+    let mut fields = HashMap::new();
+    let mut context = Vec::new();
+    let mut reasons = Vec::new();
+    let mut requests = None;
+    for (name, value) in options {
+        match name.as_str() {
+            "format" => {
+                format = match value.as_str() {
+                    "human" => Format::Human,
+                    "json" => Format::Json,
+                    _ => return crate::usage_error(&format!("unknown format `{value}`")),
+                }
+            }
+            "ctx" => match value.split_once('=') {
+                Some((key, value)) => context.push((key.to_string(), ContextValue::parse(value))),
+                None => return crate::usage_error(&format!("expected KEY=VALUE, got `{value}`")),
+            },
+            "reason" => match value
+                .split_once('=')
+                .map(|(name, code)| (name, code.parse()))
+            {
+                Some((name, Ok(code))) => reasons.push((name.to_string(), ReasonCode(code))),
+                _ => return crate::usage_error(&format!("expected NAME=CODE, got `{value}`")),
+            },
+            "requests" => requests = Some(value),
+            _ => {
+                fields.insert(name, value);
+            }
+        }
+    }
+
+    let evaluator = match Evaluator::load(Path::new(path), &reasons) {
+        Ok(evaluator) => evaluator,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let Some(requests) = requests else {
+        let request = match (
+            fields.remove("principal"),
+            fields.remove("action"),
+            fields.remove("resource"),
+        ) {
+            (Some(principal), Some(action), Some(resource)) => OwnedRequest {
+                principal,
+                action,
+                resource,
+                context,
+            },
+            _ => {
+                return crate::usage_error(
+                    "expected --principal, --action and --resource, or --requests",
+                );
+            }
+        };
+        return match evaluator.evaluate(&request) {
+            Ok(outcome) => {
+                print(format, &request, &outcome, &evaluator);
+                ExitCode::SUCCESS
+            }
+            Err(message) => {
+                eprintln!("{message}");
+                ExitCode::from(2)
+            }
+        };
+    };
+    if !fields.is_empty() || !context.is_empty() {
+        return crate::usage_error(
+            "--requests can't be combined with a request on the command line",
+        );
+    }
+
+    let input: Box<dyn BufRead> = if requests == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        match std::fs::File::open(&requests) {
+            Ok(file) => Box::new(std::io::BufReader::new(file)),
+            Err(err) => {
+                eprintln!("{requests}: {err}");
+                return ExitCode::from(2);
+            }
+        }
+    };
+
+    let mut failed = false;
+    let mut printed = false;
+    for (index, line) in input.lines().enumerate() {
+        let result = line
+            .map_err(|err| err.to_string())
+            .and_then(|line| match line.trim() {
+                "" => Ok(None),
+                line => OwnedRequest::from_json(line).map(Some),
+            })
+            .and_then(|request| match request {
+                Some(request) => Ok(Some((evaluator.evaluate(&request)?, request))),
+                None => Ok(None),
+            });
+        match result {
+            Ok(Some((outcome, request))) => {
+                if printed && format == Format::Human {
+                    println!();
+                }
+                print(format, &request, &outcome, &evaluator);
+                printed = true;
+            }
+            Ok(None) => {}
+            Err(message) => {
+                eprintln!("{requests}:{}: {message}", index + 1);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+enum ContextValue {
+    Bool(bool),
+    Int(i64),
+    String(String),
+}
+
+impl ContextValue {
+    /// Reads `true`, `false` and integers as such, and anything else as a string.
+    /// Double quotes force a string, as in `level="5"`.
+    fn parse(value: &str) -> Self {
+        if let Some(quoted) = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+        {
+            return ContextValue::String(quoted.to_string());
+        }
+        match value {
+            "true" => ContextValue::Bool(true),
+            "false" => ContextValue::Bool(false),
+            _ => match value.parse() {
+                Ok(value) => ContextValue::Int(value),
+                Err(_) => ContextValue::String(value.to_string()),
+            },
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            ContextValue::Bool(value) => json!(value),
+            ContextValue::Int(value) => json!(value),
+            ContextValue::String(value) => json!(value),
+        }
+    }
+}
+
+impl std::fmt::Display for ContextValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContextValue::Bool(value) => write!(f, "{value}"),
+            ContextValue::Int(value) => write!(f, "{value}"),
+            ContextValue::String(value) => write!(f, "{value:?}"),
+        }
+    }
+}
+
+struct OwnedRequest {
+    principal: String,
+    action: String,
+    resource: String,
+    context: Vec<(String, ContextValue)>,
+}
+
+impl OwnedRequest {
+    fn from_json(line: &str) -> Result<Self, String> {
+        let value = serde_json::Value::from_str(line).map_err(|err| err.to_string())?;
+        let field = |name: &str| match value.get(name) {
+            Some(serde_json::Value::String(value)) => Ok(value.clone()),
+            _ => Err(format!("expected `{name}` to be a string")),
+        };
+
+        let mut context = Vec::new();
+        match value.get("context") {
+            Some(serde_json::Value::Object(pairs)) => {
+                for (key, value) in pairs {
+                    let value = match value {
+                        serde_json::Value::Bool(value) => ContextValue::Bool(*value),
+                        serde_json::Value::String(value) => ContextValue::String(value.clone()),
+                        serde_json::Value::Number(number) => match number.as_i64() {
+                            Some(value) => ContextValue::Int(value),
+                            None => return Err(format!("`{key}` isn't a 64-bit integer")),
+                        },
+                        _ => return Err(format!("`{key}` must be a string, integer or boolean")),
+                    };
+                    context.push((key.clone(), value));
+                }
+            }
+            Some(_) => return Err(String::from("expected `context` to be an object")),
+            None => {}
+        }
+
+        Ok(OwnedRequest {
+            principal: field("principal")?,
+            action: field("action")?,
+            resource: field("resource")?,
+            context,
+        })
+    }
+}
+
+impl std::fmt::Display for OwnedRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "({:?} {:?} {:?})",
+            self.principal, self.action, self.resource
+        )?;
+        if !self.context.is_empty() {
+            let pairs = self
+                .context
+                .iter()
+                .map(|(key, value)| format!("{key} => {value}"))
+                .collect::<Vec<_>>();
+            write!(f, " WITH {{ {} }}", pairs.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A rule evaluated on its own, turned into an `ALLOW` so any match shows up as one.
+struct Probe {
+    policy: gate0::Policy,
+    effect: Effect,
+    reason: ReasonCode,
+    line: usize,
+    source: String,
+}
+
+struct Evaluator {
+    path: String,
+    policy: gate0::Policy,
+    probes: Vec<Probe>,
+    names: HashMap<ReasonCode, String>,
+}
+
+/// The decision for a request and the index of the probe of the rule that made it.
+struct Outcome {
+    effect: Effect,
+    reason: ReasonCode,
+    rule: Option<usize>,
+}
+
+impl Evaluator {
+    fn load(path: &Path, reasons: &[(String, ReasonCode)]) -> Result<Self, ParseError> {
+        let lookup = reasons
+            .iter()
+            .map(|(name, code)| (name.as_str(), *code))
+            .collect::<HashMap<_, _>>();
+        let with_path = |err: ParseError| ParseError {
+            path: Some(path.to_path_buf()),
+            ..err
+        };
+        let build_error = |err: gate0::PolicyError| ParseError {
+            path: Some(path.to_path_buf()),
+            line: 0,
+            column: 0,
+            message: format!("the policy failed to build: {err:?}"),
+        };
+
+        let policy = parse_policy_file(path, &lookup)?
+            .build()
+            .map_err(build_error)?;
+
+        // parse_policy_file succeeded, so neither reading nor parsing fails here.
+        let source = std::fs::read_to_string(path).unwrap_or_default();
+        let definition: PolicyDefinition = syn::parse_str(&source).map_err(ParseError::from)?;
+        let config = match definition.config {
+            Some(ref config) => build_config(config).map_err(with_path)?,
+            None => gate0::PolicyConfig::default(),
+        };
+
+        let ends = statement_ends(&source);
+        let mut probes = Vec::new();
+        for rule in &definition.rules {
+            let mut built = build_rule(rule, &lookup).map_err(with_path)?;
+            let (effect, reason) = (built.effect, built.reason);
+            built.effect = Effect::Allow;
+            let policy = gate0::Policy::builder()
+                .config(config.clone())
+                .rule(built)
+                .build()
+                .map_err(build_error)?;
+
+            probes.push(Probe {
+                policy,
+                effect,
+                reason,
+                line: rule.line(),
+                source: rule_source(&source, &ends, rule),
+            });
+        }
+
+        Ok(Evaluator {
+            path: path.display().to_string(),
+            policy,
+            probes,
+            names: reasons
+                .iter()
+                .map(|(name, code)| (*code, name.clone()))
+                .collect(),
+        })
+    }
+
+    fn evaluate(&self, request: &OwnedRequest) -> Result<Outcome, String> {
+        let context = request
+            .context
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    ContextValue::Bool(value) => gate0::Value::Bool(*value),
+                    ContextValue::Int(value) => gate0::Value::Int(*value),
+                    ContextValue::String(value) => gate0::Value::String(value),
+                };
+                (key.as_str(), value)
+            })
+            .collect::<Vec<_>>();
+        let request = gate0::Request::with_context(
+            &request.principal,
+            &request.action,
+            &request.resource,
+            &context,
+        );
+
+        let decision = self
+            .policy
+            .evaluate(&request)
+            .map_err(|err| format!("evaluation failed: {err:?}"))?;
+        let rule = self.probes.iter().position(|probe| {
+            probe.effect == decision.effect
+                && probe.reason == decision.reason
+                && probe
+                    .policy
+                    .evaluate(&request)
+                    .is_ok_and(|decision| decision.is_allow())
+        });
+
+        Ok(Outcome {
+            effect: decision.effect,
+            reason: decision.reason,
+            rule,
+        })
+    }
+}
+
+/// The byte offsets just past each top-level `;` in a policy source.
+fn statement_ends(source: &str) -> Vec<usize> {
+    TokenStream::from_str(source)
+        .map(|tokens| {
+            tokens
+                .into_iter()
+                .filter_map(|tree| match tree {
+                    TokenTree::Punct(punct) if punct.as_char() == ';' => {
+                        Some(punct.span().byte_range().end)
+                    }
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The source of a rule written as a statement of its own, or the rule as it was expanded
+/// when its source doesn't spell it out: rules from blocks and `ELSE` chains, and the
+/// ones `COMBINE FIRST_MATCH` rewrites.
+fn rule_source(source: &str, ends: &[usize], rule: &RuleDefinition) -> String {
+    let expanded = rule.to_string();
+    let start = rule.span.byte_range().start;
+    let end = ends
+        .iter()
+        .copied()
+        .find(|&end| end > start)
+        .unwrap_or(source.len());
+    let tokens = |text: &str| TokenStream::from_str(text).map(|tokens| tokens.to_string());
+    match (tokens(&source[start..end]), tokens(&expanded)) {
+        (Ok(written), Ok(expanded)) if written == expanded => dedent(source, start..end),
+        _ => expanded,
+    }
+}
+
+/// The source of a rule, with the indentation of its first line removed from the rest.
+fn dedent(source: &str, range: std::ops::Range<usize>) -> String {
+    let line_start = source[..range.start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let indent = &source[line_start..range.start];
+    source[range]
+        .lines()
+        .map(|line| line.strip_prefix(indent).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn effect_name(effect: Effect) -> &'static str {
+    match effect {
+        Effect::Allow => "ALLOW",
+        Effect::Deny => "DENY",
+    }
+}
+
+fn print(format: Format, request: &OwnedRequest, outcome: &Outcome, evaluator: &Evaluator) {
+    let name = evaluator.names.get(&outcome.reason);
+    let rule = outcome.rule.map(|index| &evaluator.probes[index]);
+    match format {
+        Format::Human => {
+            println!("request: {request}");
+            println!("effect:  {}", effect_name(outcome.effect));
+            match name {
+                Some(name) => println!("reason:  {} ({name})", outcome.reason.0),
+                None => println!("reason:  {}", outcome.reason.0),
+            }
+            match rule {
+                Some(rule) => {
+                    println!("rule:    {}:{}", evaluator.path, rule.line);
+                    for line in rule.source.lines() {
+                        println!("    {line}");
+                    }
+                }
+                None => println!("rule:    none matched"),
+            }
+        }
+        Format::Json => {
+            let context = request
+                .context
+                .iter()
+                .map(|(key, value)| (key.clone(), value.to_json()))
+                .collect::<serde_json::Map<_, _>>();
+            println!(
+                "{}",
+                json!({
+                    "request": {
+                        "principal": request.principal,
+                        "action": request.action,
+                        "resource": request.resource,
+                        "context": context,
+                    },
+                    "effect": effect_name(outcome.effect),
+                    "reason": outcome.reason.0,
+                    "reason_name": name,
+                    "rule": rule.map(|rule| json!({
+                        "path": evaluator.path,
+                        "line": rule.line,
+                        "source": rule.source,
+                    })),
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! `gate0-dsl`, command-line tools for gate0 DSL policies.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+mod check;
+mod eval;
+
+const USAGE: &str = "\
+usage: gate0-dsl <command> [options]
+
+commands:
+  check [--format human|json] [PATH]...
+      parse and validate policies
+  eval POLICY --principal P --action A --resource R [--ctx KEY=VALUE]...
+              [--reason NAME=CODE]... [--format human|json]
+  eval POLICY --requests FILE [--reason NAME=CODE]... [--format human|json]
+      evaluate requests against a .gate0 policy";
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("check") => check::run(args),
+        Some("eval") => eval::run(args),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Some(command) => usage_error(&format!("unknown command `{command}`")),
+        None => usage_error("missing command"),
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("{message}\n{USAGE}");
+    ExitCode::from(2)
+}
+
+/// The arguments of a command, split into options and positional arguments.
+struct Args {
+    /// Option names, without the leading `--`, and values, in command line order.
+    options: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+/// Splits `args` into options and positional arguments. Every option takes a value,
+/// given as `--name value` or `--name=value`, and has to be one of `names`.
+fn parse_args(mut args: impl Iterator<Item = String>, names: &[&str]) -> Result<Args, String> {
+    let mut options = Vec::new();
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        let Some(option) = arg.strip_prefix("--") else {
+            positional.push(arg);
+            continue;
+        };
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (option, args.next()),
+        };
+        if !names.contains(&name) {
+            return Err(format!("unknown option `--{name}`"));
+        }
+        let value = value.ok_or_else(|| format!("`--{name}` needs a value"))?;
+        options.push((name.to_string(), value));
+    }
+    Ok(Args {
+        options,
+        positional,
+    })
+}
+
+/// Collects the files to work on. Paths given explicitly are always included;
+/// inside directories only `.rs` and `.gate0` files are, skipping `target` and
+/// hidden directories.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        if entry.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                collect_files(&entry, files)?;
+            }
+        } else if matches!(
+            entry.extension().and_then(|ext| ext.to_str()),
+            Some("rs" | "gate0")
+        ) {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
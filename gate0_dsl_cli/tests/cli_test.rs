@@ -0,0 +1,235 @@
+use std::process::{Command, Output};
+
+fn gate0_dsl(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gate0-dsl"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_check_clean() {
+    let output = gate0_dsl(&["check", "tests/fixtures/clean.gate0"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn test_check_human() {
+    let output = gate0_dsl(&[
+        "check",
+        "tests/fixtures/errors.gate0",
+        "tests/fixtures/warnings.gate0",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        stdout(&output),
+        "tests/fixtures/errors.gate0:2:7: error: expected ANY, *, tuple, or struct\n\
+         tests/fixtures/warnings.gate0:1:1: warning: an empty array never matches, so the rule never applies\n\
+         tests/fixtures/warnings.gate0:2:25: warning: `action` is given more than once; only the last value is used\n"
+    );
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "1 error, 2 warnings\n"
+    );
+
+    let output = gate0_dsl(&["check", "tests/fixtures/warnings.gate0"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_check_json_rust_source() {
+    let output = gate0_dsl(&["check", "--format", "json", "tests/fixtures/policies.rs"]);
+    assert_eq!(output.status.code(), Some(2));
+    let diagnostics = stdout(&output)
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        [
+            serde_json::json!({
+                "path": "tests/fixtures/policies.rs",
+                "line": 5,
+                "column": 9,
+                "severity": "error",
+                "message": "the condition has depth 3, more than max_condition_depth (2)",
+            }),
+            serde_json::json!({
+                "path": "tests/fixtures/policies.rs",
+                "line": 10,
+                "column": 23,
+                "severity": "warning",
+                "message": "an empty array never matches, so the rule never applies",
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_check_policy_fn() {
+    let output = gate0_dsl(&["check", "tests/fixtures/params.rs"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        stdout(&output),
+        "tests/fixtures/params.rs:3:34: error: the parameter `region` is never used\n\
+         tests/fixtures/params.rs:15:5: warning: `policy_builder!` isn't checked, as its body declares `macro_rules!` metavariables\n\
+         tests/fixtures/params.rs:23:34: error: `$tenant` isn't declared in PARAMS\n\
+         tests/fixtures/params.rs:28:16: error: PARAMS is only available in `policy_fn!`\n\
+         tests/fixtures/params.rs:40:13: error: EXPECT isn't supported with PARAMS, as its outcome depends on the values passed in\n\
+         tests/fixtures/params.rs:36:5: error: EXPLAIN isn't supported with PARAMS, as it builds the policy without any values\n"
+    );
+}
+
+#[test]
+fn test_check_for_loops() {
+    let output = gate0_dsl(&["check", "tests/fixtures/loops.rs"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        stdout(&output),
+        "tests/fixtures/loops.rs:5:55: error: `$zone` isn't declared in PARAMS\n\
+         tests/fixtures/loops.rs:5:55: error: `$zone` isn't declared in PARAMS\n"
+    );
+}
+
+const REASONS: [&str; 4] = [
+    "--reason",
+    "ADMIN_ACCESS=100",
+    "--reason",
+    "MEMBER_READ=101",
+];
+
+#[test]
+fn test_eval() {
+    let mut args = vec![
+        "eval",
+        "tests/fixtures/saas.gate0",
+        "--principal",
+        "bob",
+        "--action",
+        "read",
+        "--resource",
+        "doc",
+        "--ctx",
+        "role=member",
+        "--ctx",
+        "level=5",
+    ];
+    args.extend(REASONS);
+    let output = gate0_dsl(&args);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        r#"request: ("bob" "read" "doc") WITH { role => "member", level => 5 }
+effect:  ALLOW
+reason:  101 (MEMBER_READ)
+rule:    tests/fixtures/saas.gate0:4
+    ALLOW { action: ["read", "list"] }
+        WHERE { role EQ "member" } => MEMBER_READ;
+"#
+    );
+
+    let output = gate0_dsl(&[
+        "eval",
+        "tests/fixtures/saas.gate0",
+        "--principal=bob",
+        "--action=read",
+        "--resource=doc",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "tests/fixtures/saas.gate0:3:34: unknown reason code `ADMIN_ACCESS`\n"
+    );
+}
+
+#[test]
+fn test_eval_batch() {
+    let mut args = vec![
+        "eval",
+        "tests/fixtures/saas.gate0",
+        "--requests",
+        "tests/fixtures/requests.jsonl",
+        "--format",
+        "json",
+    ];
+    args.extend(REASONS);
+    let output = gate0_dsl(&args);
+    assert_eq!(output.status.code(), Some(0));
+
+    let decisions = stdout(&output)
+        .lines()
+        .map(|line| {
+            let decision = serde_json::from_str::<serde_json::Value>(line).unwrap();
+            (
+                decision["request"]["principal"].to_string(),
+                decision["effect"].to_string(),
+                decision["reason"].to_string(),
+                decision["reason_name"].to_string(),
+                decision["rule"]["line"].to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let expected = [
+        (r#""alice""#, r#""ALLOW""#, "100", r#""ADMIN_ACCESS""#, "2"),
+        (r#""bob""#, r#""ALLOW""#, "101", r#""MEMBER_READ""#, "4"),
+        (r#""eve""#, r#""DENY""#, "3", "null", "7"),
+        (r#""carl""#, r#""DENY""#, "0", "null", "null"),
+    ]
+    .map(|(principal, effect, reason, name, line)| {
+        (
+            principal.to_string(),
+            effect.to_string(),
+            reason.to_string(),
+            name.to_string(),
+            line.to_string(),
+        )
+    });
+    assert_eq!(decisions, expected);
+}
+
+#[test]
+fn test_eval_blocks() {
+    let output = gate0_dsl(&[
+        "eval",
+        "tests/fixtures/blocks.gate0",
+        "--requests",
+        "tests/fixtures/blocks.jsonl",
+        "--format",
+        "json",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let rules = stdout(&output)
+        .lines()
+        .map(|line| {
+            let decision = serde_json::from_str::<serde_json::Value>(line).unwrap();
+            (
+                decision["rule"]["line"].as_u64().unwrap(),
+                decision["rule"]["source"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let expected = [
+        (
+            5,
+            r#"ALLOW { action: "export" } WHERE { NOT (plan EQ "premium") AND role EQ "admin" } => 2;"#,
+        ),
+        (8, r#"DENY { action: "delete" } WHERE { tenant EQ "acme" } => 3;"#),
+        (13, r#"ALLOW { action: ["read"] } WHERE { role EQ "member" } => 4;"#),
+        (16, r#"ALLOW { action: "purge" } WHERE { role EQ "admin" } => 5;"#),
+        (20, r#"DENY { action: "audit" } WHERE { region NEQ "eu" } => 7;"#),
+        (23, r#"ALLOW ("svc-b" * *) => 8;"#),
+        (
+            25,
+            "ALLOW { action: \"list\" }\n    WHERE { role AT_LEAST \"member\" } => 9;",
+        ),
+    ]
+    .map(|(line, source)| (line, source.to_string()));
+    assert_eq!(rules, expected);
+}
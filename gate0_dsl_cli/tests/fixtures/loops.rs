@@ -0,0 +1,8 @@
+fn regions() -> gate0::PolicyBuilder {
+    policy_builder! {
+        FOR region IN ["eu", "us"] {
+            ALLOW ("svc-$region" * *) WHERE { region EQ $region } => 1;
+            DENY ("svc-$region" * *) WHERE { zone EQ $zone } => 2;
+        }
+    }
+}
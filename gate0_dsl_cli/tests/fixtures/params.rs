@@ -0,0 +1,41 @@
+policy_fn! {
+    pub fn tenant_policy;
+    PARAMS(tenant: &'static str, region: &'static str);
+
+    ALLOW { action: "read" } WHERE { tenant EQ $tenant } => 1;
+}
+
+macro_rules! tenant_rules {
+    ($($rule:tt)*) => {
+        policy_builder! { $($rule)* }
+    };
+}
+
+fn wrapped() -> gate0::PolicyBuilder {
+    policy_builder! { $($rule:tt)* }
+}
+
+policy_fn! {
+    fn saas_policy;
+    CATALOG static SAAS_RULES;
+    EXPLAIN fn explain_saas;
+
+    ALLOW ANY WHERE { tenant EQ $tenant } => 1;
+}
+
+fn tenant_builder() -> gate0::PolicyBuilder {
+    policy_builder! {
+        PARAMS(tenant: &'static str);
+
+        ALLOW ANY WHERE { tenant EQ $tenant } => 1;
+    }
+}
+
+policy_fn! {
+    fn checked_policy;
+    EXPLAIN fn explain_checked;
+    PARAMS(tenant: &'static str);
+
+    ALLOW ANY WHERE { tenant EQ $tenant } => 1;
+    EXPECT ("alice" "read" "doc") WITH { tenant => "acme" } => ALLOW 1;
+}
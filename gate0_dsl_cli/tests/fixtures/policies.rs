@@ -0,0 +1,11 @@
+fn policy() -> gate0::PolicyBuilder {
+    policy_builder! {
+        CONFIG { max_condition_depth: 2 };
+        ALLOW ANY WHERE { role EQ "admin" } => 1;
+        ALLOW ANY WHERE { NOT (role EQ "admin" AND level EQ 3) } => 2;
+    }
+}
+
+fn other() -> gate0::PolicyBuilder {
+    policy_builder! { DENY { resource: [] } => 3; }
+}
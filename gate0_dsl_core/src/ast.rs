@@ -0,0 +1,1164 @@
+//! The syntax tree of the policy DSL and of the companion macro inputs.
+//!
+//! Every node keeps the tokens it was parsed from, so spans stay available for
+//! diagnostics, and renders back to DSL syntax through `Display`.
+
+use proc_macro2::{Span, TokenStream};
+use syn::{Expr, Ident, LitInt, LitStr};
+
+/// The key-value pairs of a `ctx!` invocation or a request context.
+pub struct CtxDefinition {
+    pub pairs: Vec<(LitStr, Value)>,
+}
+
+/// The body of `policy_builder!`: optional `PARAMS`, `USE`, `CONFIG`, `ROLES` and `COMBINE`, then rules
+/// and `EXPECT` lines in any order.
+pub struct PolicyDefinition {
+    /// The parameters declared with `PARAMS(...)`, only accepted by `policy_fn!`.
+    pub params: Vec<Param>,
+    pub config: Option<ConfigBlock>,
+    pub roles: Option<RoleHierarchy>,
+    /// Whether `COMBINE FIRST_MATCH;` rewrote the rules so that only the first rule
+    /// matching a request applies to it.
+    pub first_match: bool,
+    pub use_builder: Option<Expr>,
+    pub rules: Vec<RuleDefinition>,
+    pub expectations: Vec<Expectation>,
+}
+
+impl PolicyDefinition {
+    /// Renders the rules whose targets match `request`, in definition order.
+    pub fn rules_matching(&self, request: &RequestSpec) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|rule| {
+                rule.target.matches(
+                    &request.principal.value(),
+                    &request.action.value(),
+                    &request.resource.value(),
+                )
+            })
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Renders the rules as a Markdown table, one row per rule in definition order,
+    /// with the rules' doc comments as descriptions.
+    pub fn markdown(&self) -> String {
+        fn cell(text: &str) -> String {
+            text.replace('|', "\\|")
+        }
+
+        let mut markdown = String::from(
+            "| # | Effect | Principals | Actions | Resources | Conditions | Reason | Description |\n\
+             | --- | --- | --- | --- | --- | --- | --- | --- |\n",
+        );
+        for (index, rule) in self.rules.iter().enumerate() {
+            let (principal, action, resource) = match rule.target {
+                TargetSpec::Any(_) => (None, None, None),
+                TargetSpec::Tuple(ref t) => {
+                    (Some(&t.principal), Some(&t.action), Some(&t.resource))
+                }
+                TargetSpec::Struct(ref s) => {
+                    (s.field("principal"), s.field("action"), s.field("resource"))
+                }
+            };
+            let [principals, actions, resources] = [principal, action, resource]
+                .map(|value| value.map_or_else(|| String::from("any"), FieldValue::describe));
+            let mut conditions = rule
+                .condition
+                .as_ref()
+                .map_or_else(|| String::from("always"), |c| c.expr.describe());
+            if let Some(predicate) = rule.cfg_text() {
+                conditions.push_str(&format!(", only with `cfg({predicate})`"));
+            }
+            markdown.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | `{}` | {} |\n",
+                index + 1,
+                rule.effect,
+                cell(&principals),
+                cell(&actions),
+                cell(&resources),
+                cell(&conditions),
+                rule.reason_code,
+                cell(&rule.description()),
+            ));
+        }
+        markdown
+    }
+}
+
+/// An `RBAC { attr: role; admin => *; member => ["read", "list"]; }` table, allowing
+/// every role the actions of its row.
+pub struct RbacTable {
+    /// The context attribute holding the role.
+    pub attr: LitStr,
+    /// The reason code of the rows without one of their own, given as `reason: <code>;`.
+    /// Without it, such rows get `0`.
+    pub reason: Option<ReasonCode>,
+    pub rows: Vec<RbacRow>,
+}
+
+pub struct RbacRow {
+    pub role: LitStr,
+    pub actions: FieldValue,
+    pub reason: Option<ReasonCode>,
+}
+
+impl RbacTable {
+    /// One `ALLOW { action: <actions> } WHERE { <attr> EQ "<role>" }` rule per row,
+    /// starting on the row's line.
+    pub fn rules(&self) -> Vec<RuleDefinition> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let span = row.role.span();
+                let reason_code = row
+                    .reason
+                    .as_ref()
+                    .or(self.reason.as_ref())
+                    .cloned()
+                    .unwrap_or_else(|| ReasonCode::Literal(LitInt::new("0", span)));
+                RuleDefinition {
+                    docs: Vec::new(),
+                    attributes: Vec::new(),
+                    cfg: Vec::new(),
+                    span,
+                    effect: Effect::Allow,
+                    target: TargetSpec::Struct(StructTarget {
+                        fields: vec![StructTargetField {
+                            name: Ident::new("action", span),
+                            value: row.actions.clone(),
+                        }],
+                    }),
+                    condition: Some(ConditionExpr {
+                        expr: Box::new(Condition::Equals {
+                            attr: self.attr.value(),
+                            value: Value::Str(row.role.clone()),
+                        }),
+                    }),
+                    reason_code,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The most combinations of declared column values `TABLE COMPLETE` checks.
+pub const MAX_TABLE_COMBINATIONS: usize = 4096;
+
+/// A `TABLE (role, region, action) { ("admin", *, *) => ALLOW 1; }` decision table.
+/// Columns named `principal`, `action` or `resource` match that field of the target,
+/// the others compare a context attribute.
+pub struct DecisionTable {
+    pub keyword: Ident,
+    /// Whether the table is written `TABLE COMPLETE`, so every combination of the
+    /// declared column values has to be covered by a row.
+    pub complete: bool,
+    pub columns: Vec<TableColumn>,
+    pub rows: Vec<TableRow>,
+}
+
+/// A column of a decision table, with the values it can take when declared as
+/// `name: [values]`.
+pub struct TableColumn {
+    pub name: LitStr,
+    pub values: Option<Vec<Value>>,
+}
+
+impl TableColumn {
+    pub fn is_target_field(&self) -> bool {
+        matches!(
+            self.name.value().as_str(),
+            "principal" | "action" | "resource"
+        )
+    }
+}
+
+pub struct TableRow {
+    /// The opening parenthesis of the row's cells.
+    pub span: Span,
+    pub cells: Vec<TableCell>,
+    pub effect: Effect,
+    pub reason: ReasonCode,
+}
+
+/// A cell of a decision table: `*`, a value, or an array of values.
+pub enum TableCell {
+    Any(Span),
+    Value(Value),
+    Array(Vec<Value>),
+}
+
+impl TableCell {
+    /// The values the cell matches, or `None` when it matches anything.
+    pub fn values(&self) -> Option<&[Value]> {
+        match self {
+            TableCell::Any(_) => None,
+            TableCell::Value(value) => Some(std::slice::from_ref(value)),
+            TableCell::Array(values) => Some(values),
+        }
+    }
+}
+
+impl std::fmt::Display for TableCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableCell::Any(_) => write!(f, "*"),
+            TableCell::Value(value) => write!(f, "{value}"),
+            TableCell::Array(values) => {
+                let values = values.iter().map(ToString::to_string).collect::<Vec<_>>();
+                write!(f, "[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+impl DecisionTable {
+    /// One rule per row, matching the target columns' cells in its target and the
+    /// other columns' cells in its condition, ANDed in the order of the columns.
+    pub fn rules(&self) -> Vec<RuleDefinition> {
+        self.rows
+            .iter()
+            .map(|row| {
+                let mut fields = Vec::new();
+                let mut condition: Option<Condition> = None;
+                for (column, cell) in self.columns.iter().zip(&row.cells) {
+                    let Some(values) = cell.values() else {
+                        continue;
+                    };
+                    if column.is_target_field() {
+                        // Parsing only lets strings into the target columns.
+                        let strings = values.iter().filter_map(|value| match value {
+                            Value::Str(lit) => Some(lit.clone()),
+                            _ => None,
+                        });
+                        let value = match cell {
+                            TableCell::Value(Value::Str(lit)) => FieldValue::Literal(lit.clone()),
+                            _ => FieldValue::Array(strings.collect()),
+                        };
+                        fields.push(StructTargetField {
+                            name: Ident::new(&column.name.value(), column.name.span()),
+                            value,
+                        });
+                    } else {
+                        let check = Condition::any_of(&column.name.value(), values);
+                        condition = Some(match condition {
+                            Some(left) => Condition::And(Box::new(left), Box::new(check)),
+                            None => check,
+                        });
+                    }
+                }
+                let target = if fields.is_empty() {
+                    TargetSpec::Any(Ident::new("ANY", row.span))
+                } else {
+                    TargetSpec::Struct(StructTarget { fields })
+                };
+                RuleDefinition {
+                    docs: Vec::new(),
+                    attributes: Vec::new(),
+                    cfg: Vec::new(),
+                    span: row.span,
+                    effect: row.effect.clone(),
+                    target,
+                    condition: condition.map(|expr| ConditionExpr {
+                        expr: Box::new(expr),
+                    }),
+                    reason_code: row.reason.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// The combinations of the declared column values that no row covers, each with
+    /// one value per declared column. A row covers a combination when each of its cells
+    /// in those columns is `*` or holds the value, whatever its other cells are.
+    ///
+    /// `None` when there are more than [`MAX_TABLE_COMBINATIONS`] combinations to check.
+    pub fn uncovered(&self) -> Option<Vec<Vec<&Value>>> {
+        let declared = self
+            .columns
+            .iter()
+            .enumerate()
+            .filter_map(|(index, column)| Some((index, column.values.as_ref()?)))
+            .collect::<Vec<_>>();
+        if declared.is_empty() {
+            return Some(Vec::new());
+        }
+        let count = declared
+            .iter()
+            .try_fold(1usize, |count, (_, values)| count.checked_mul(values.len()));
+        if count.is_none_or(|count| count > MAX_TABLE_COMBINATIONS) {
+            return None;
+        }
+
+        let mut combinations: Vec<Vec<&Value>> = vec![Vec::new()];
+        for (_, values) in &declared {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push(value);
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations.retain(|combination| {
+            !self.rows.iter().any(|row| {
+                declared.iter().zip(combination).all(|((index, _), value)| {
+                    match row.cells[*index].values() {
+                        Some(values) => values.iter().any(|cell| cell.same_as(value)),
+                        None => true,
+                    }
+                })
+            })
+        });
+        Some(combinations)
+    }
+}
+
+/// A `MATCH role { "admin" => ALLOW ANY => 1; _ => DENY ANY => 2; }` block, dispatching
+/// on the value of a context attribute.
+pub struct MatchBlock {
+    pub attr: LitStr,
+    pub arms: Vec<MatchArm>,
+}
+
+pub struct MatchArm {
+    /// The value the arm matches, or `None` for the `_` arm.
+    pub value: Option<Value>,
+    pub chain: RuleChain,
+}
+
+impl MatchBlock {
+    /// The rules of every arm, with `<attr> EQ <value>` ANDed in front of their conditions,
+    /// or `<attr> NEQ <value>` for the value of every other arm in the `_` arm.
+    pub fn rules(self) -> Vec<RuleDefinition> {
+        let attr = self.attr.value();
+        let values = self
+            .arms
+            .iter()
+            .filter_map(|arm| arm.value.clone())
+            .collect::<Vec<_>>();
+        let mut rules = Vec::new();
+        for arm in self.arms {
+            let guard = match arm.value {
+                Some(value) => Some(Condition::Equals {
+                    attr: attr.clone(),
+                    value,
+                }),
+                None => values.iter().fold(None, |guard, value| {
+                    let other = Condition::NotEquals {
+                        attr: attr.clone(),
+                        value: value.clone(),
+                    };
+                    Some(match guard {
+                        Some(guard) => Condition::And(Box::new(guard), Box::new(other)),
+                        None => other,
+                    })
+                }),
+            };
+            for mut rule in arm.chain.rules() {
+                if let Some(ref guard) = guard {
+                    let expr = match rule.condition.take() {
+                        Some(condition) => Condition::And(Box::new(guard.clone()), condition.expr),
+                        None => guard.clone(),
+                    };
+                    rule.condition = Some(ConditionExpr {
+                        expr: Box::new(expr),
+                    });
+                }
+                rules.push(rule);
+            }
+        }
+        rules
+    }
+}
+
+/// A `ROLES { owner > admin > member, owner > billing };` declaration: chains of roles,
+/// each above the roles after it.
+pub struct RoleHierarchy {
+    pub chains: Vec<Vec<LitStr>>,
+}
+
+impl RoleHierarchy {
+    /// `role` and every role above it, in the order they are first declared, or `None`
+    /// when `role` isn't declared.
+    pub fn at_least(&self, role: &str) -> Option<Vec<&LitStr>> {
+        let declared = self.chains.iter().flatten().collect::<Vec<_>>();
+        if !declared.iter().any(|lit| lit.value() == role) {
+            return None;
+        }
+
+        let mut names = vec![role.to_string()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for pair in self.chains.iter().flat_map(|chain| chain.windows(2)) {
+                let (higher, lower) = (pair[0].value(), pair[1].value());
+                if names.contains(&lower) && !names.contains(&higher) {
+                    names.push(higher);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut roles = Vec::new();
+        for lit in declared {
+            if let Some(index) = names.iter().position(|name| *name == lit.value()) {
+                names.swap_remove(index);
+                roles.push(lit);
+            }
+        }
+        Some(roles)
+    }
+}
+
+pub struct ConfigBlock {
+    pub fields: Vec<ConfigField>,
+}
+
+pub struct ConfigField {
+    pub name: Ident,
+    pub value: Expr,
+}
+
+pub struct Expectation {
+    pub request: RequestSpec,
+    pub expected: ExpectedDecision,
+}
+
+/// A request written as `("principal" "action" "resource") WITH { key => value, ... }`.
+///
+/// The parentheses and the `WITH` keyword are optional, as is the context block.
+pub struct RequestSpec {
+    pub principal: LitStr,
+    pub action: LitStr,
+    pub resource: LitStr,
+    pub context: CtxDefinition,
+}
+
+impl RequestSpec {
+    pub fn target_text(&self) -> String {
+        format!(
+            "({:?} {:?} {:?})",
+            self.principal.value(),
+            self.action.value(),
+            self.resource.value()
+        )
+    }
+
+    pub fn context_text(&self) -> String {
+        if self.context.pairs.is_empty() {
+            return String::from("{}");
+        }
+        let pairs = self
+            .context
+            .pairs
+            .iter()
+            .map(|(key, value)| format!("{} => {value}", key.value()))
+            .collect::<Vec<_>>();
+        format!("{{ {} }}", pairs.join(", "))
+    }
+}
+
+impl std::fmt::Display for RequestSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.target_text())?;
+        if !self.context.pairs.is_empty() {
+            write!(f, " WITH {}", self.context_text())?;
+        }
+        Ok(())
+    }
+}
+
+/// The decision an expectation asserts: `ALLOW`, `DENY`, optionally followed by a
+/// reason code, either bare (`ALLOW ADMIN`) or parenthesized (`ALLOW(ADMIN)`).
+pub struct ExpectedDecision {
+    pub effect: Effect,
+    pub reason: Option<ReasonCode>,
+}
+
+impl std::fmt::Display for ExpectedDecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.effect)?;
+        if let Some(ref reason) = self.reason {
+            write!(f, " {reason}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The body of `policy_tests!`.
+pub struct PolicyTests {
+    pub policy: Expr,
+    pub rows: Vec<TestRow>,
+}
+
+pub struct TestRow {
+    pub name: Option<Ident>,
+    pub request: RequestSpec,
+    pub expected: ExpectedDecision,
+}
+
+/// The arguments of `assert_decision!`.
+pub struct AssertDecision {
+    pub policy: Expr,
+    pub request: RequestSpec,
+    pub expected: ExpectedDecision,
+}
+
+/// The argument of `include_policy!`: a policy file path, relative to the manifest
+/// directory of the including crate.
+pub struct IncludePolicy {
+    pub path: LitStr,
+}
+
+/// The body of `policy_coverage!`.
+pub struct PolicyCoverage {
+    pub vis: syn::Visibility,
+    pub name: Ident,
+    pub type_name: Ident,
+    pub policy: PolicyDefinition,
+}
+
+/// The body of `policy_catalog!`.
+pub struct PolicyCatalog {
+    pub vis: syn::Visibility,
+    pub name: Ident,
+    pub policy: PolicyDefinition,
+}
+
+/// The body of `policy_explain!`.
+pub struct PolicyExplain {
+    pub vis: syn::Visibility,
+    pub name: Ident,
+    pub policy: PolicyDefinition,
+}
+
+/// The body of `policy_fn!`.
+pub struct PolicyFn {
+    pub vis: syn::Visibility,
+    pub name: Ident,
+    /// The items declared after the function, generated from the same rules.
+    pub companions: Vec<Companion>,
+    pub policy: PolicyDefinition,
+}
+
+/// An item declared in `policy_fn!` with `CATALOG`, `DOC`, `COVERAGE` or `EXPLAIN`,
+/// followed by the header the macro generating it on its own takes, like
+/// `CATALOG pub static API_RULES;`.
+pub struct Companion {
+    pub keyword: Ident,
+    pub vis: syn::Visibility,
+    pub name: Ident,
+    pub kind: CompanionKind,
+}
+
+pub enum CompanionKind {
+    /// `CATALOG [pub] static NAME;`, the rule metadata of `policy_catalog!`.
+    Catalog,
+    /// `DOC [pub] const NAME;`, the Markdown of `policy_doc!`.
+    Doc,
+    /// `COVERAGE [pub] static NAME: TypeName;`, the tracker of `policy_coverage!`.
+    Coverage { type_name: Ident },
+    /// `EXPLAIN [pub] fn name;`, the function of `policy_explain!`.
+    Explain,
+}
+
+/// A parameter in `PARAMS(name: type, ...)`, referenced as `$name` in `WHERE` clauses.
+pub struct Param {
+    pub name: Ident,
+    pub ty: syn::Type,
+}
+
+pub struct RuleDefinition {
+    /// The text of the rule's doc comments, one entry per line.
+    pub docs: Vec<String>,
+    /// The `#[name = "value"]` metadata attributes, in the order written.
+    pub attributes: Vec<RuleAttribute>,
+    /// The predicates of the `#[cfg(...)]` attributes on the rule and its enclosing
+    /// groups, outermost first. The rule only exists when all of them hold.
+    pub cfg: Vec<TokenStream>,
+    /// Where the rule starts, after its doc comments.
+    pub span: Span,
+    pub effect: Effect,
+    pub target: TargetSpec,
+    pub condition: Option<ConditionExpr>,
+    pub reason_code: ReasonCode,
+}
+
+/// A rule followed by `ELSE` branches on the same target, like
+/// `ALLOW .. WHERE { .. } => A ELSE IF { .. } DENY => B ELSE DENY => C;`.
+pub struct RuleChain {
+    pub rule: RuleDefinition,
+    pub branches: Vec<ElseBranch>,
+}
+
+pub struct ElseBranch {
+    /// The `ELSE` keyword.
+    pub span: Span,
+    /// The condition of an `ELSE IF` branch.
+    pub condition: Option<ConditionExpr>,
+    pub effect: Effect,
+    pub reason_code: ReasonCode,
+}
+
+impl RuleChain {
+    /// The rule, then one rule per branch, each with the target, doc comments and
+    /// `#[cfg(...)]` predicates of the rule. A branch's condition ANDs the negations of
+    /// the conditions before it with its own.
+    pub fn rules(self) -> Vec<RuleDefinition> {
+        let RuleChain { rule, branches } = self;
+        let mut rules = Vec::new();
+        let mut negations = rule
+            .condition
+            .as_ref()
+            .map(|condition| Condition::Not(condition.expr.clone()));
+        for branch in branches {
+            let Some(earlier) = negations.take() else {
+                // Parsing rejects branches after an unconditional one.
+                break;
+            };
+            let condition = match branch.condition {
+                Some(ref own) => {
+                    negations = Some(Condition::And(
+                        Box::new(earlier.clone()),
+                        Box::new(Condition::Not(own.expr.clone())),
+                    ));
+                    Condition::And(Box::new(earlier), own.expr.clone())
+                }
+                None => earlier,
+            };
+            rules.push(RuleDefinition {
+                docs: rule.docs.clone(),
+                attributes: Vec::new(),
+                cfg: rule.cfg.clone(),
+                span: branch.span,
+                effect: branch.effect,
+                target: rule.target.clone(),
+                condition: Some(ConditionExpr {
+                    expr: Box::new(condition),
+                }),
+                reason_code: branch.reason_code,
+            });
+        }
+        rules.insert(0, rule);
+        rules
+    }
+}
+
+impl RuleDefinition {
+    /// The line the rule starts on in its source.
+    pub fn line(&self) -> usize {
+        self.span.start().line
+    }
+
+    /// The value of the metadata attribute `name`.
+    pub fn attribute(&self, name: &str) -> Option<&LitStr> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| &attribute.value)
+    }
+
+    /// The `cfg` predicate the rule exists under, or `None` when it is unconditional.
+    /// Several predicates are combined with `all(...)`.
+    pub fn cfg_text(&self) -> Option<String> {
+        let predicates = self.cfg.iter().map(ToString::to_string).collect::<Vec<_>>();
+        match predicates.len() {
+            0 => None,
+            1 => predicates.into_iter().next(),
+            _ => Some(format!("all({})", predicates.join(", "))),
+        }
+    }
+
+    /// The doc comments joined into one paragraph.
+    pub fn description(&self) -> String {
+        self.docs
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl std::fmt::Display for RuleDefinition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.effect)?;
+        if !self.target.is_omitted() {
+            write!(f, " {}", self.target)?;
+        }
+        if let Some(ref condition) = self.condition {
+            write!(f, " WHERE {condition}")?;
+        }
+        write!(f, " => {};", self.reason_code)
+    }
+}
+
+/// A metadata attribute on a rule, such as `#[id = "billing-read"]` or
+/// `#[owner = "team-billing"]`.
+#[derive(Clone)]
+pub struct RuleAttribute {
+    pub name: Ident,
+    pub value: LitStr,
+}
+
+#[derive(Clone)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+impl std::fmt::Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Effect::Allow => write!(f, "ALLOW"),
+            Effect::Deny => write!(f, "DENY"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum TargetSpec {
+    /// `ANY` or `*`; the latter is parsed as an identifier named `star`. A target left
+    /// out of a rule inside a `FOR` block is parsed as an identifier named `omitted`.
+    Any(Ident),
+    Tuple(TupleTarget),
+    Struct(StructTarget),
+}
+
+impl TargetSpec {
+    /// Whether the rule this target belongs to was written without one, taking the
+    /// target of its `FOR` block.
+    pub fn is_omitted(&self) -> bool {
+        matches!(self, TargetSpec::Any(ident) if ident == "omitted")
+    }
+
+    /// The span of the target's first token.
+    pub fn span(&self) -> Span {
+        match self {
+            TargetSpec::Any(ident) => ident.span(),
+            TargetSpec::Tuple(t) => t.principal.span(),
+            TargetSpec::Struct(s) => s
+                .fields
+                .first()
+                .map_or_else(Span::call_site, |field| field.name.span()),
+        }
+    }
+
+    /// The span `name` is written at: its field name in a struct target, its value in
+    /// a tuple target, and the whole target otherwise.
+    pub fn field_span(&self, name: &str) -> Span {
+        let span = match self {
+            TargetSpec::Any(_) => None,
+            TargetSpec::Tuple(t) => match name {
+                "principal" => Some(t.principal.span()),
+                "action" => Some(t.action.span()),
+                "resource" => Some(t.resource.span()),
+                _ => None,
+            },
+            TargetSpec::Struct(s) => s
+                .fields
+                .iter()
+                .rev()
+                .find(|field| field.name == name)
+                .map(|field| field.name.span()),
+        };
+        span.unwrap_or_else(|| self.span())
+    }
+
+    /// The value `name` is restricted to, `None` when any value matches.
+    pub fn field(&self, name: &str) -> Option<&FieldValue> {
+        let value = match self {
+            TargetSpec::Any(_) => None,
+            TargetSpec::Tuple(t) => match name {
+                "principal" => Some(&t.principal),
+                "action" => Some(&t.action),
+                "resource" => Some(&t.resource),
+                _ => None,
+            },
+            TargetSpec::Struct(s) => s.field(name),
+        };
+        value.filter(|value| !matches!(value, FieldValue::Any(_)))
+    }
+
+    /// Whether a request with the given principal, action and resource falls
+    /// within this target, mirroring gate0's matcher semantics.
+    pub fn matches(&self, principal: &str, action: &str, resource: &str) -> bool {
+        match self {
+            TargetSpec::Any(_) => true,
+            TargetSpec::Tuple(t) => {
+                t.principal.matches(principal)
+                    && t.action.matches(action)
+                    && t.resource.matches(resource)
+            }
+            TargetSpec::Struct(s) => {
+                s.field("principal").is_none_or(|v| v.matches(principal))
+                    && s.field("action").is_none_or(|v| v.matches(action))
+                    && s.field("resource").is_none_or(|v| v.matches(resource))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TargetSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TargetSpec::Any(ident) if ident == "star" => write!(f, "*"),
+            TargetSpec::Any(ident) if ident == "omitted" => Ok(()),
+            TargetSpec::Any(_) => write!(f, "ANY"),
+            TargetSpec::Tuple(t) => write!(f, "({} {} {})", t.principal, t.action, t.resource),
+            TargetSpec::Struct(s) => {
+                let fields = s
+                    .fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.name, field.value))
+                    .collect::<Vec<_>>();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TupleTarget {
+    pub principal: FieldValue,
+    pub action: FieldValue,
+    pub resource: FieldValue,
+}
+
+#[derive(Clone)]
+pub struct StructTarget {
+    pub fields: Vec<StructTargetField>,
+}
+
+impl StructTarget {
+    /// The last value given for `name`, matching how the target is expanded.
+    pub fn field(&self, name: &str) -> Option<&FieldValue> {
+        self.fields
+            .iter()
+            .rev()
+            .find(|field| field.name == name)
+            .map(|field| &field.value)
+    }
+}
+
+#[derive(Clone)]
+pub struct StructTargetField {
+    pub name: Ident,
+    pub value: FieldValue,
+}
+
+impl StructTargetField {
+    pub fn valid_field_names() -> &'static [&'static str] {
+        &["principal", "action", "resource"]
+    }
+}
+
+#[derive(Clone)]
+pub enum FieldValue {
+    Literal(LitStr),
+    Array(Vec<LitStr>),
+    /// `ANY` or `*`; the latter is parsed as an identifier named `star`.
+    Any(Ident),
+}
+
+impl FieldValue {
+    pub fn span(&self) -> Span {
+        match self {
+            FieldValue::Literal(lit) => lit.span(),
+            FieldValue::Array(arr) => arr.first().map_or_else(Span::call_site, LitStr::span),
+            FieldValue::Any(ident) => ident.span(),
+        }
+    }
+
+    pub fn literals(&self) -> Vec<String> {
+        match self {
+            FieldValue::Literal(lit) => vec![lit.value()],
+            FieldValue::Array(arr) => arr.iter().map(LitStr::value).collect(),
+            FieldValue::Any(_) => Vec::new(),
+        }
+    }
+
+    /// The matched values in plain language, as `any` or a list of code spans.
+    pub fn describe(&self) -> String {
+        match self {
+            FieldValue::Any(_) => String::from("any"),
+            _ => self
+                .literals()
+                .iter()
+                .map(|value| format!("`{value}`"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            FieldValue::Literal(lit) => lit.value() == value,
+            FieldValue::Array(arr) => arr.iter().any(|lit| lit.value() == value),
+            FieldValue::Any(_) => true,
+        }
+    }
+}
+
+impl std::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldValue::Literal(lit) => write!(f, "{:?}", lit.value()),
+            FieldValue::Array(arr) => {
+                let values = arr
+                    .iter()
+                    .map(|lit| format!("{:?}", lit.value()))
+                    .collect::<Vec<_>>();
+                write!(f, "[{}]", values.join(", "))
+            }
+            FieldValue::Any(ident) if ident == "star" => write!(f, "*"),
+            FieldValue::Any(_) => write!(f, "ANY"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum ReasonCode {
+    Literal(LitInt),
+    Ident(Ident),
+}
+
+impl std::fmt::Display for ReasonCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReasonCode::Literal(lit) => write!(f, "{lit}"),
+            ReasonCode::Ident(ident) => write!(f, "{ident}"),
+        }
+    }
+}
+
+/// A braced `WHERE` block.
+pub struct ConditionExpr {
+    pub expr: Box<Condition>,
+}
+
+impl std::fmt::Display for ConditionExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{ {} }}", self.expr)
+    }
+}
+
+#[derive(Clone)]
+pub enum Condition {
+    Equals {
+        attr: String,
+        value: Value,
+    },
+    NotEquals {
+        attr: String,
+        value: Value,
+    },
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    /// `attr AT_LEAST "role"`, which holds when `attr` is the role or one above it in the
+    /// `ROLES` hierarchy. `roles` lists those roles once the whole policy is parsed.
+    AtLeast {
+        attr: String,
+        role: LitStr,
+        roles: Vec<Value>,
+    },
+    True,
+    False,
+}
+
+impl Condition {
+    /// The nesting depth as gate0 counts it, where a leaf has depth 1.
+    pub fn depth(&self) -> usize {
+        match self {
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                1 + left.depth().max(right.depth())
+            }
+            Condition::Not(inner) => 1 + inner.depth(),
+            // Built as `NOT (attr EQ value)`.
+            Condition::NotEquals { .. } => 2,
+            Condition::AtLeast { attr, roles, .. } => Condition::any_of(attr, roles).depth(),
+            _ => 1,
+        }
+    }
+
+    /// A balanced `OR` of `attr EQ value` over `values`, the expansion of `AT_LEAST`.
+    /// With no values, it never holds.
+    pub fn any_of(attr: &str, values: &[Value]) -> Condition {
+        match values {
+            [] => Condition::False,
+            [value] => Condition::Equals {
+                attr: attr.to_string(),
+                value: value.clone(),
+            },
+            _ => {
+                let (left, right) = values.split_at(values.len().div_ceil(2));
+                Condition::Or(
+                    Box::new(Condition::any_of(attr, left)),
+                    Box::new(Condition::any_of(attr, right)),
+                )
+            }
+        }
+    }
+
+    /// Collects every attribute compared with `EQ`, `NEQ` or `AT_LEAST`, with the
+    /// distinct values it is compared against, keyed and sorted by their DSL rendering.
+    pub fn collect_comparisons<'a>(
+        &'a self,
+        attributes: &mut std::collections::BTreeMap<String, Vec<(String, &'a Value)>>,
+    ) {
+        let mut insert = |attr: &String, value: &'a Value| {
+            let values = attributes.entry(attr.clone()).or_default();
+            let label = value.to_string();
+            if let Err(index) = values.binary_search_by(|(existing, _)| existing.cmp(&label)) {
+                values.insert(index, (label, value));
+            }
+        };
+        match self {
+            Condition::Equals { attr, value } | Condition::NotEquals { attr, value } => {
+                insert(attr, value);
+            }
+            Condition::AtLeast { attr, roles, .. } => {
+                for role in roles {
+                    insert(attr, role);
+                }
+            }
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                left.collect_comparisons(attributes);
+                right.collect_comparisons(attributes);
+            }
+            Condition::Not(inner) => inner.collect_comparisons(attributes),
+            Condition::True | Condition::False => {}
+        }
+    }
+}
+
+impl Condition {
+    /// Renders the condition in plain language, e.g.
+    /// `role is "member" and not (suspended is true)`.
+    pub fn describe(&self) -> String {
+        fn operand(condition: &Condition) -> String {
+            match condition {
+                Condition::And(..) | Condition::Or(..) => format!("({})", condition.describe()),
+                _ => condition.describe(),
+            }
+        }
+
+        match self {
+            Condition::Equals { attr, value } => format!("{attr} is {value}"),
+            Condition::NotEquals { attr, value } => format!("{attr} is not {value}"),
+            Condition::And(left, right) => format!("{} and {}", operand(left), operand(right)),
+            Condition::Or(left, right) => format!("{} or {}", operand(left), operand(right)),
+            Condition::Not(inner) => match **inner {
+                Condition::Equals { .. }
+                | Condition::NotEquals { .. }
+                | Condition::AtLeast { .. } => format!("not ({})", inner.describe()),
+                _ => format!("not {}", operand(inner)),
+            },
+            Condition::AtLeast { attr, role, .. } => {
+                format!("{attr} is at least {:?}", role.value())
+            }
+            Condition::True => String::from("always"),
+            Condition::False => String::from("never"),
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn attr(attr: &str) -> String {
+            let is_ident = attr
+                .chars()
+                .next()
+                .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+                && attr
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+            if is_ident {
+                attr.to_string()
+            } else {
+                format!("{attr:?}")
+            }
+        }
+
+        // The parser only accepts a parenthesized condition or a boolean after NOT,
+        // and nested AND/OR chains are grouped explicitly to keep their shape.
+        fn operand(condition: &Condition) -> String {
+            match condition {
+                Condition::And(..) | Condition::Or(..) => format!("({condition})"),
+                _ => condition.to_string(),
+            }
+        }
+
+        match self {
+            Condition::Equals { attr: name, value } => write!(f, "{} EQ {value}", attr(name)),
+            Condition::NotEquals { attr: name, value } => {
+                write!(f, "{} NEQ {value}", attr(name))
+            }
+            Condition::And(left, right) => {
+                write!(f, "{} AND {}", operand(left), operand(right))
+            }
+            Condition::Or(left, right) => write!(f, "{} OR {}", operand(left), operand(right)),
+            Condition::Not(inner) => match **inner {
+                Condition::True | Condition::False => write!(f, "NOT {inner}"),
+                _ => write!(f, "NOT ({inner})"),
+            },
+            Condition::AtLeast {
+                attr: name, role, ..
+            } => {
+                write!(f, "{} AT_LEAST {:?}", attr(name), role.value())
+            }
+            Condition::True => write!(f, "true"),
+            Condition::False => write!(f, "false"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Value {
+    Int(LitInt),
+    Str(LitStr),
+    Bool(syn::LitBool),
+    /// `$name`, a parameter of a `policy_fn!`. Only compared against in `WHERE` clauses.
+    Param(Ident),
+}
+
+impl Value {
+    pub fn span(&self) -> Span {
+        match self {
+            Value::Int(lit) => lit.span(),
+            Value::Str(lit) => lit.span(),
+            Value::Bool(lit) => lit.span,
+            Value::Param(name) => name.span(),
+        }
+    }
+
+    /// Whether both denote the same value, however they are written: `1` and `0x1`
+    /// are the same integer, `"a"` and `r"a"` the same string.
+    pub fn same_as(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(lit), Value::Int(other)) => lit.base10_digits() == other.base10_digits(),
+            (Value::Str(lit), Value::Str(other)) => lit.value() == other.value(),
+            (Value::Bool(lit), Value::Bool(other)) => lit.value == other.value,
+            (Value::Param(name), Value::Param(other)) => name == other,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{value}"),
+            Value::Str(value) => write!(f, "{:?}", value.value()),
+            Value::Bool(value) => write!(f, "{}", value.value),
+            Value::Param(name) => write!(f, "${name}"),
+        }
+    }
+}
@@ -0,0 +1,1569 @@
+//! Code generation for `policy_builder!` and its companion macros.
+//!
+//! The generated code refers to `::gate0` by absolute path, so it only compiles in
+//! crates depending on gate0.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{ToTokens, quote};
+use syn::{Expr, Ident};
+
+use crate::ast::*;
+use crate::validate::{Limits, Severity, validate_source};
+
+impl CtxDefinition {
+    pub fn expand(&self) -> TokenStream2 {
+        let pairs = &self.pairs;
+        let keys = pairs.iter().map(|(k, _)| k);
+        let values = pairs.iter().map(|(_, v)| v.expand());
+
+        quote! {
+            &{
+                let context: [(&str, ::gate0::Value); _] = [
+                    #(
+                        (#keys, #values),
+                    )*
+                ];
+                context
+            }
+        }
+    }
+}
+
+impl PolicyDefinition {
+    pub fn expand(&self) -> TokenStream2 {
+        let builder_init = if let Some(ref builder_expr) = self.use_builder {
+            quote! { let mut builder = #builder_expr; }
+        } else {
+            quote! { let mut builder = ::gate0::Policy::builder(); }
+        };
+
+        let config_setup = self.expand_config();
+        let rule_additions = self.expand_rules();
+        let rule_count_check = self.expand_rule_count_check();
+
+        quote! {
+            {
+                #rule_count_check
+                #builder_init
+                #config_setup
+                #(#rule_additions)*
+                builder
+            }
+        }
+    }
+
+    /// Checks `max_rules` at compile time once the `cfg` predicates are known, when the
+    /// rules exceed it only if conditional rules are enabled. The validator reports
+    /// the case where the unconditional rules alone exceed it.
+    pub fn expand_rule_count_check(&self) -> TokenStream2 {
+        let Some(max_rules) = Limits::for_policy(self).max_rules else {
+            return quote! {};
+        };
+        let unconditional = self.rules.iter().filter(|rule| rule.cfg.is_empty()).count();
+        if self.rules.len() <= max_rules || unconditional > max_rules {
+            return quote! {};
+        }
+
+        let conditional = self
+            .rules
+            .iter()
+            .filter(|rule| !rule.cfg.is_empty())
+            .map(|rule| {
+                let predicates = &rule.cfg;
+                quote! { + cfg!(all(#(#predicates),*)) as usize }
+            });
+        let message = format!("the enabled rules are more than max_rules ({max_rules})");
+        quote! {
+            const _: () = ::std::assert!(#unconditional #(#conditional)* <= #max_rules, #message);
+        }
+    }
+
+    /// Expands to the Markdown documentation of the rules as a string literal.
+    pub fn expand_doc(&self) -> TokenStream2 {
+        let markdown = self.markdown();
+        quote! { #markdown }
+    }
+
+    pub fn expand_config(&self) -> TokenStream2 {
+        if let Some(ref cfg) = self.config {
+            let config_fields = &cfg.fields;
+            quote! {
+                builder = builder.config(::gate0::PolicyConfig {
+                    #(#config_fields)*
+                    ..::gate0::PolicyConfig::default()
+                });
+            }
+        } else {
+            quote! {}
+        }
+    }
+
+    pub fn expand_rules(&self) -> Vec<TokenStream2> {
+        self.rules
+            .iter()
+            .map(|rule| {
+                let rule_expr = rule.expand();
+                rule.expand_gated(quote! {
+                    builder = builder.rule(#rule_expr);
+                })
+            })
+            .collect()
+    }
+
+    /// Expands to the builder, wrapped when there are `EXPECT` lines or rules with
+    /// `#[name = "value"]` attributes. The wrapper's `build` checks the expectations
+    /// against the built policy in test builds, its `self_test` builds the policy and
+    /// returns the lines it doesn't satisfy, and `into_builder` gives back the plain
+    /// builder. With attributes, `catalog` returns the rules' metadata as
+    /// `policy_catalog!` declares it.
+    pub fn expand_checked(&self) -> TokenStream2 {
+        let builder = self.expand();
+        let has_attributes = self.rules.iter().any(|rule| !rule.attributes.is_empty());
+        if self.expectations.is_empty() && !has_attributes {
+            return builder;
+        }
+
+        let checks = self
+            .expectations
+            .iter()
+            .map(|expectation| expectation.expand(self));
+        let catalog = has_attributes.then(|| {
+            let name = Ident::new("CATALOG", proc_macro2::Span::call_site());
+            let catalog = PolicyCatalog::expand_static(&syn::Visibility::Inherited, &name, self);
+            quote! {
+                fn catalog(&self) -> &'static [::gate0_dsl_core::meta::RuleMeta] {
+                    #catalog
+                    #name
+                }
+            }
+        });
+
+        quote! {
+            {
+                #[derive(Debug)]
+                #[allow(dead_code)]
+                struct Mismatch {
+                    expectation: &'static str,
+                    expected: String,
+                    actual: String,
+                    matching_rules: &'static str,
+                }
+
+                #[must_use]
+                struct CheckedPolicyBuilder(::gate0::PolicyBuilder);
+
+                #[allow(dead_code)]
+                impl CheckedPolicyBuilder {
+                    fn build(self) -> Result<::gate0::Policy, ::gate0::PolicyError> {
+                        let policy = self.0.build()?;
+                        #[cfg(test)]
+                        {
+                            if let Err(mismatches) = Self::check(&policy) {
+                                let mut message = String::from("policy expectations failed:");
+                                for mismatch in &mismatches {
+                                    message.push_str(&format!(
+                                        "\n  {}\n    expected: {}\n    actual:   {}{}",
+                                        mismatch.expectation,
+                                        mismatch.expected,
+                                        mismatch.actual,
+                                        mismatch.matching_rules,
+                                    ));
+                                }
+                                panic!("{}", message);
+                            }
+                        }
+                        Ok(policy)
+                    }
+
+                    fn self_test(self) -> Result<(), Vec<Mismatch>> {
+                        let policy = self.0.build().map_err(|err| {
+                            vec![Mismatch {
+                                expectation: "<policy>",
+                                expected: String::from("policy to build"),
+                                actual: format!("{err:?}"),
+                                matching_rules: "",
+                            }]
+                        })?;
+                        Self::check(&policy)
+                    }
+
+                    fn into_builder(self) -> ::gate0::PolicyBuilder {
+                        self.0
+                    }
+
+                    #catalog
+
+                    #[allow(unused_variables, unused_mut)]
+                    fn check(policy: &::gate0::Policy) -> Result<(), Vec<Mismatch>> {
+                        let mut mismatches = Vec::new();
+                        #(#checks)*
+
+                        if mismatches.is_empty() {
+                            Ok(())
+                        } else {
+                            Err(mismatches)
+                        }
+                    }
+                }
+
+                CheckedPolicyBuilder(#builder)
+            }
+        }
+    }
+}
+
+impl Expectation {
+    pub fn expand(&self, policy: &PolicyDefinition) -> TokenStream2 {
+        let text = format!("EXPECT {} => {}", self.request, self.expected);
+        let matching_rules = matching_rules_report(Some(policy), &self.request, "    ");
+        let request = self.request.expand_bindings();
+        let matches = self.expected.expand_matches(&quote! { decision });
+        let expected = self.expected.expand_debug();
+
+        quote! {
+            {
+                #request
+                match policy.evaluate(&request) {
+                    Ok(decision) => {
+                        if !(#matches) {
+                            mismatches.push(Mismatch {
+                                expectation: #text,
+                                expected: #expected,
+                                actual: format!("{:?} {:?}", decision.effect, decision.reason),
+                                matching_rules: #matching_rules,
+                            });
+                        }
+                    }
+                    Err(err) => mismatches.push(Mismatch {
+                        expectation: #text,
+                        expected: #expected,
+                        actual: format!("evaluation failed: {err:?}"),
+                        matching_rules: #matching_rules,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+impl RequestSpec {
+    /// Expands to `let` statements binding `context` and a `request` borrowing it.
+    pub fn expand_bindings(&self) -> TokenStream2 {
+        let principal = &self.principal;
+        let action = &self.action;
+        let resource = &self.resource;
+        let context = self.context.expand();
+
+        quote! {
+            let context = #context;
+            let request = ::gate0::Request::with_context(#principal, #action, #resource, context);
+        }
+    }
+}
+
+/// Lists the rules of `policy` whose targets match `request` as an extra report
+/// section, or nothing when the rules aren't known at expansion time.
+pub fn matching_rules_report(
+    policy: Option<&PolicyDefinition>,
+    request: &RequestSpec,
+    indent: &str,
+) -> String {
+    let Some(policy) = policy else {
+        return String::new();
+    };
+    let rules = policy.rules_matching(request);
+    if rules.is_empty() {
+        return format!("\n{indent}rules with matching targets: none");
+    }
+    let mut report = format!("\n{indent}rules with matching targets:");
+    for rule in rules {
+        report.push_str(&format!("\n{indent}  {rule}"));
+    }
+    report
+}
+
+/// Expands to a `String` expression listing the rules of the built `policy` whose targets
+/// match the `request` in scope, for policies whose rules aren't known at expansion time.
+///
+/// gate0 only exposes the rules of a policy through its `Debug` output, so the rules and
+/// their matchers are read from there. Nothing is listed if it can't be read.
+pub fn expand_matching_rules(policy: &TokenStream2, indent: &str) -> TokenStream2 {
+    let header = format!("\n{indent}rules with matching targets:");
+    let none = format!("{header} none");
+    let line = format!("\n{indent}  ");
+
+    quote! {
+        {
+            // Splits `text` at the commas outside of brackets and string literals.
+            fn split(text: &str) -> Vec<&str> {
+                let (mut parts, mut start, mut depth) = (Vec::new(), 0, 0);
+                let (mut quoted, mut escaped) = (false, false);
+                for (index, ch) in text.char_indices() {
+                    match ch {
+                        _ if escaped => escaped = false,
+                        '\\' if quoted => escaped = true,
+                        '"' => quoted = !quoted,
+                        _ if quoted => {}
+                        '(' | '[' | '{' => depth += 1,
+                        ')' | ']' | '}' => depth -= 1,
+                        ',' if depth == 0 => {
+                            parts.push(text[start..index].trim());
+                            start = index + 1;
+                        }
+                        _ => {}
+                    }
+                }
+                parts.push(text[start..].trim());
+                parts.retain(|part| !part.is_empty());
+                parts
+            }
+
+            // The text between the first opening and the last closing bracket.
+            fn inner(text: &str) -> Option<&str> {
+                let start = text.find(['(', '[', '{'])?;
+                let end = text.rfind([')', ']', '}'])?;
+                text.get(start + 1..end)
+            }
+
+            fn field<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+                split(inner(text)?)
+                    .into_iter()
+                    .find_map(|part| part.strip_prefix(name)?.strip_prefix(": "))
+            }
+
+            fn matches(matcher: &str, value: &str) -> bool {
+                let value = format!("{value:?}");
+                matcher == "Any"
+                    || (matcher.starts_with("Exact(") && inner(matcher) == Some(value.as_str()))
+                    || (matcher.starts_with("OneOf(")
+                        && inner(matcher)
+                            .and_then(inner)
+                            .is_some_and(|values| split(values).contains(&value.as_str())))
+            }
+
+            let debug = format!("{:?}", #policy);
+            let rules = field(&debug, "rules")
+                .and_then(inner)
+                .map(split)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|rule| {
+                    field(rule, "target").is_some_and(|target| {
+                        [
+                            ("principal", request.principal),
+                            ("action", request.action),
+                            ("resource", request.resource),
+                        ]
+                        .iter()
+                        .all(|(name, value)| {
+                            field(target, name).is_some_and(|matcher| matches(matcher, value))
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            if field(&debug, "rules").is_none() {
+                String::new()
+            } else if rules.is_empty() {
+                String::from(#none)
+            } else {
+                let mut report = String::from(#header);
+                for rule in rules {
+                    report.push_str(#line);
+                    report.push_str(rule);
+                }
+                report
+            }
+        }
+    }
+}
+
+/// Finds a `policy_builder!` invocation behind an expression such as
+/// `policy_builder![...].build().unwrap()`, so its rules can be reported.
+pub fn inline_policy(expr: &Expr) -> Option<PolicyDefinition> {
+    match expr {
+        Expr::Macro(mac) => {
+            let name = mac.mac.path.segments.last()?;
+            if name.ident == "policy_builder" {
+                mac.mac.parse_body().ok()
+            } else {
+                None
+            }
+        }
+        Expr::MethodCall(call) => inline_policy(&call.receiver),
+        Expr::Paren(expr) => inline_policy(&expr.expr),
+        Expr::Reference(expr) => inline_policy(&expr.expr),
+        _ => None,
+    }
+}
+
+/// Evaluates a request against `policy` and panics with a readable report when the
+/// decision doesn't match. Shared by `policy_tests!` and `assert_decision!`.
+pub struct DecisionCheck<'a> {
+    pub request: &'a RequestSpec,
+    pub expected: &'a ExpectedDecision,
+    pub policy: Option<PolicyDefinition>,
+}
+
+impl DecisionCheck<'_> {
+    pub fn expand(&self, policy: &TokenStream2) -> TokenStream2 {
+        let target = self.request.target_text();
+        let context = self.request.context_text();
+        let matching_rules = match self.policy {
+            Some(ref rules) => {
+                let report = matching_rules_report(Some(rules), self.request, "  ");
+                quote! { #report }
+            }
+            None => expand_matching_rules(policy, "  "),
+        };
+        let request = self.request.expand_bindings();
+        let matches = self.expected.expand_matches(&quote! { decision });
+        let expected = self.expected.expand_debug();
+        let report = quote! {
+            "decision mismatch\n  request:  {}\n  context:  {}\n  expected: {}\n  actual:   {}{}"
+        };
+
+        quote! {
+            {
+                #request
+                let decision = match #policy.evaluate(&request) {
+                    Ok(decision) => decision,
+                    Err(err) => panic!(
+                        #report,
+                        #target,
+                        #context,
+                        #expected,
+                        format!("evaluation failed: {err:?}"),
+                        #matching_rules,
+                    ),
+                };
+                if !(#matches) {
+                    panic!(
+                        #report,
+                        #target,
+                        #context,
+                        #expected,
+                        format!("{:?} {:?}", decision.effect, decision.reason),
+                        #matching_rules,
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl ExpectedDecision {
+    /// Expands to a `bool` expression checking `decision` against this expectation.
+    pub fn expand_matches(&self, decision: &TokenStream2) -> TokenStream2 {
+        let effect = match self.effect {
+            Effect::Allow => quote! { #decision.is_allow() },
+            Effect::Deny => quote! { #decision.is_deny() },
+        };
+        if let Some(ref reason) = self.reason {
+            let reason = reason.expand();
+            quote! { #effect && #decision.reason == #reason }
+        } else {
+            effect
+        }
+    }
+
+    /// Expands to a `String` describing the expectation in `Debug` terms, so it
+    /// lines up with the formatted actual decision.
+    pub fn expand_debug(&self) -> TokenStream2 {
+        let effect = self.effect.expand();
+        if let Some(ref reason) = self.reason {
+            let reason = reason.expand();
+            quote! { format!("{:?} {:?}", #effect, #reason) }
+        } else {
+            quote! { format!("{:?}", #effect) }
+        }
+    }
+}
+
+impl PolicyTests {
+    pub fn expand(&self) -> TokenStream2 {
+        let mut used_names = Vec::new();
+        let tests = self.rows.iter().map(|row| {
+            let name = row.test_name(&mut used_names);
+            row.expand(&name, &self.policy)
+        });
+
+        quote! {
+            #(#tests)*
+        }
+    }
+}
+
+impl TestRow {
+    /// Uses the explicit name if there is one, otherwise derives a snake case name
+    /// from the request, appending a counter when two rows would collide.
+    pub fn test_name(&self, used_names: &mut Vec<String>) -> Ident {
+        if let Some(ref name) = self.name {
+            used_names.push(name.to_string());
+            return name.clone();
+        }
+
+        let mut parts = vec![
+            self.request.principal.value(),
+            self.request.action.value(),
+            self.request.resource.value(),
+        ];
+        for (key, value) in &self.request.context.pairs {
+            parts.push(key.value());
+            parts.push(value.to_string());
+        }
+
+        let mut base = String::new();
+        for part in parts {
+            for ch in part.chars() {
+                if ch.is_ascii_alphanumeric() {
+                    base.push(ch.to_ascii_lowercase());
+                } else if !base.is_empty() && !base.ends_with('_') {
+                    base.push('_');
+                }
+            }
+            if !base.is_empty() && !base.ends_with('_') {
+                base.push('_');
+            }
+        }
+        let base = base.trim_end_matches('_');
+        let base = if base.is_empty() || base.starts_with(|ch: char| ch.is_ascii_digit()) {
+            format!("row_{base}")
+        } else {
+            base.to_string()
+        };
+
+        let mut name = base.clone();
+        let mut counter = 2;
+        while used_names.contains(&name) {
+            name = format!("{base}_{counter}");
+            counter += 1;
+        }
+        used_names.push(name.clone());
+
+        Ident::new(&name, self.request.principal.span())
+    }
+
+    pub fn expand(&self, name: &Ident, policy: &Expr) -> TokenStream2 {
+        let check = DecisionCheck {
+            request: &self.request,
+            expected: &self.expected,
+            policy: inline_policy(policy),
+        }
+        .expand(&quote! { policy });
+
+        quote! {
+            #[test]
+            fn #name() {
+                let policy = #policy;
+                #check
+            }
+        }
+    }
+}
+
+/// The axes of a decision table: the literal values mentioned in a policy, sorted,
+/// each followed by a sentinel standing in for every other value.
+pub struct DecisionMatrix {
+    principals: Vec<String>,
+    actions: Vec<String>,
+    resources: Vec<String>,
+    attributes: std::collections::BTreeMap<String, Vec<(String, TokenStream2)>>,
+}
+
+impl DecisionMatrix {
+    const OTHER: &'static str = "<other>";
+
+    pub fn new(policy: &PolicyDefinition) -> Self {
+        let mut principals = std::collections::BTreeSet::new();
+        let mut actions = std::collections::BTreeSet::new();
+        let mut resources = std::collections::BTreeSet::new();
+        let mut attributes = std::collections::BTreeMap::new();
+
+        for rule in &policy.rules {
+            let (principal, action, resource) = match rule.target {
+                TargetSpec::Any(_) => (None, None, None),
+                TargetSpec::Tuple(ref t) => {
+                    (Some(&t.principal), Some(&t.action), Some(&t.resource))
+                }
+                TargetSpec::Struct(ref s) => {
+                    (s.field("principal"), s.field("action"), s.field("resource"))
+                }
+            };
+            principals.extend(principal.map(FieldValue::literals).unwrap_or_default());
+            actions.extend(action.map(FieldValue::literals).unwrap_or_default());
+            resources.extend(resource.map(FieldValue::literals).unwrap_or_default());
+
+            if let Some(ref condition) = rule.condition {
+                condition.expr.collect_comparisons(&mut attributes);
+            }
+        }
+
+        let with_other = |values: std::collections::BTreeSet<String>| {
+            let mut other = String::from(Self::OTHER);
+            while values.contains(&other) {
+                other.push('_');
+            }
+            values.into_iter().chain([other]).collect()
+        };
+
+        DecisionMatrix {
+            principals: with_other(principals),
+            actions: with_other(actions),
+            resources: with_other(resources),
+            attributes: attributes
+                .into_iter()
+                .map(|(attr, values)| {
+                    let values = values
+                        .into_iter()
+                        .map(|(label, value): (String, &Value)| (label, value.expand()))
+                        .collect();
+                    (attr, values)
+                })
+                .collect(),
+        }
+    }
+
+    /// Expands to a block building the policy and rendering one row per combination.
+    ///
+    /// For attributes, the sentinel means the attribute isn't set in the context.
+    pub fn expand(&self, policy: &PolicyDefinition) -> TokenStream2 {
+        let builder = policy.expand();
+        let principals = &self.principals;
+        let actions = &self.actions;
+        let resources = &self.resources;
+        let attr_names = self.attributes.keys().collect::<Vec<_>>();
+        let attr_labels = self.attributes.values().map(|values| {
+            let labels = values
+                .iter()
+                .map(|(label, _)| label.as_str())
+                .chain([Self::OTHER]);
+            quote! { &[#(#labels),*] }
+        });
+        let attr_values = self
+            .attributes
+            .values()
+            .enumerate()
+            .flat_map(|(attr, values)| {
+                values.iter().enumerate().map(move |(option, (_, value))| {
+                    quote! { (#attr, #option) => Some(#value), }
+                })
+            });
+        let attr_count = self.attributes.len();
+
+        quote! {
+            {
+                let policy = #builder
+                    .build()
+                    .expect("decision_matrix!: the policy failed to build");
+                let principals: &[&str] = &[#(#principals),*];
+                let actions: &[&str] = &[#(#actions),*];
+                let resources: &[&str] = &[#(#resources),*];
+                let attr_names: [&str; #attr_count] = [#(#attr_names),*];
+                let attr_labels: [&[&str]; #attr_count] = [#(#attr_labels),*];
+                #[allow(unreachable_patterns)]
+                let attr_value = |attr: usize, option: usize| match (attr, option) {
+                    #(#attr_values)*
+                    _ => None,
+                };
+
+                let mut header = vec!["principal", "action", "resource"];
+                header.extend(attr_names);
+                header.push("decision");
+                let mut rows = vec![header.iter().map(|cell| cell.to_string()).collect::<Vec<_>>()];
+
+                for principal in principals {
+                    for action in actions {
+                        for resource in resources {
+                            let mut options = [0usize; #attr_count];
+                            loop {
+                                let context = options
+                                    .iter()
+                                    .enumerate()
+                                    .filter_map(|(attr, &option)| {
+                                        attr_value(attr, option).map(|value| (attr_names[attr], value))
+                                    })
+                                    .collect::<Vec<_>>();
+                                let request = ::gate0::Request::with_context(
+                                    principal, action, resource, &context,
+                                );
+                                let decision = match policy.evaluate(&request) {
+                                    Ok(decision) => format!("{:?} {:?}", decision.effect, decision.reason),
+                                    Err(err) => format!("error: {err:?}"),
+                                };
+
+                                let mut row = vec![
+                                    principal.to_string(),
+                                    action.to_string(),
+                                    resource.to_string(),
+                                ];
+                                row.extend(
+                                    options
+                                        .iter()
+                                        .enumerate()
+                                        .map(|(attr, &option)| attr_labels[attr][option].to_string()),
+                                );
+                                row.push(decision);
+                                rows.push(row);
+
+                                let mut attr = options.len();
+                                while attr > 0 {
+                                    attr -= 1;
+                                    options[attr] += 1;
+                                    if options[attr] < attr_labels[attr].len() {
+                                        break;
+                                    }
+                                    options[attr] = 0;
+                                }
+                                if options.iter().all(|&option| option == 0) {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                let mut widths = vec![0; rows[0].len()];
+                for row in &rows {
+                    for (width, cell) in widths.iter_mut().zip(row) {
+                        *width = (*width).max(cell.len());
+                    }
+                }
+                let mut table = String::new();
+                for (index, row) in rows.iter().enumerate() {
+                    let cells = row
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, width)| format!("{cell:width$}"))
+                        .collect::<Vec<_>>();
+                    table.push_str(cells.join(" | ").trim_end());
+                    table.push('\n');
+                    if index == 0 {
+                        let rule = widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>();
+                        table.push_str(&rule.join("-+-"));
+                        table.push('\n');
+                    }
+                }
+                table
+            }
+        }
+    }
+}
+
+impl PolicyCoverage {
+    pub fn expand(&self) -> TokenStream2 {
+        Self::expand_tracker(
+            &self.vis,
+            &self.name,
+            &self.type_name,
+            &self.policy,
+            self.policy.expand(),
+        )
+    }
+
+    /// Expands to the tracker of `policy`, whose `builder()` evaluates to `builder`.
+    pub fn expand_tracker(
+        vis: &syn::Visibility,
+        name: &Ident,
+        type_name: &Ident,
+        policy: &PolicyDefinition,
+        builder: TokenStream2,
+    ) -> TokenStream2 {
+        let rule_count = policy.rules.len();
+        let config_setup = policy.expand_config();
+        let probes = policy.rules.iter().map(|rule| {
+            let probe = rule.expand_probe();
+            let probe = quote! {
+                Some({
+                    let mut builder = ::gate0::Policy::builder();
+                    #config_setup
+                    builder
+                        .rule(#probe)
+                        .build()
+                        .expect("policy_coverage!: a rule failed to build")
+                })
+            };
+            if rule.cfg.is_empty() {
+                return probe;
+            }
+            let predicates = &rule.cfg;
+            quote! {
+                {
+                    #[cfg(all(#(#predicates),*))]
+                    let probe = #probe;
+                    #[cfg(not(all(#(#predicates),*)))]
+                    let probe = None;
+                    probe
+                }
+            }
+        });
+        let rules = policy.rules.iter().map(|rule| {
+            let line = rule.line() as u32;
+            let reason = rule.reason_code.to_string();
+            let text = rule.to_string();
+            quote! { (file!(), #line, #reason, #text) }
+        });
+
+        quote! {
+            #vis struct #type_name {
+                policy: ::gate0::Policy,
+                /// One probe per rule, `None` for rules disabled by `#[cfg]`.
+                probes: Vec<Option<::gate0::Policy>>,
+                matches: [::std::sync::atomic::AtomicUsize; #rule_count],
+            }
+
+            #vis static #name: ::std::sync::LazyLock<#type_name> =
+                ::std::sync::LazyLock::new(#type_name::new);
+
+            impl #type_name {
+                /// File, line, reason code and DSL text of every rule, in rule order.
+                pub const RULES: [(&'static str, u32, &'static str, &'static str); #rule_count] =
+                    [#(#rules),*];
+
+                fn new() -> Self {
+                    let policy = Self::builder()
+                        .build()
+                        .expect("policy_coverage!: the policy failed to build");
+                    let probes = vec![#(#probes),*];
+
+                    Self {
+                        policy,
+                        probes,
+                        matches: [const { ::std::sync::atomic::AtomicUsize::new(0) }; #rule_count],
+                    }
+                }
+
+                /// The builder of the policy made of the rules, as `policy_builder!` expands
+                /// them.
+                pub fn builder() -> ::gate0::PolicyBuilder {
+                    #builder
+                }
+
+                /// The policy made of the rules, built once with the tracker.
+                pub fn policy(&self) -> &::gate0::Policy {
+                    &self.policy
+                }
+
+                /// Records `request` and evaluates it with the policy.
+                pub fn evaluate(
+                    &self,
+                    request: &::gate0::Request,
+                ) -> Result<::gate0::Decision, ::gate0::PolicyError> {
+                    self.record(request);
+                    self.policy.evaluate(request)
+                }
+
+                /// Counts a match for every rule whose target and condition match `request`.
+                pub fn record(&self, request: &::gate0::Request) {
+                    for (index, probe) in self.probes.iter().enumerate() {
+                        if let Some(probe) = probe
+                            && probe.evaluate(request).is_ok_and(|decision| decision.is_allow())
+                        {
+                            self.matches[index].fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);
+                        }
+                    }
+                }
+
+                /// How many recorded requests matched the rule at `index`.
+                pub fn matches(&self, index: usize) -> usize {
+                    self.matches[index].load(::std::sync::atomic::Ordering::Relaxed)
+                }
+
+                /// Match counts summed over the rules sharing a reason code.
+                pub fn matches_by_reason(&self) -> ::std::collections::BTreeMap<&'static str, usize> {
+                    let mut by_reason = ::std::collections::BTreeMap::new();
+                    for (index, (_, _, reason, _)) in Self::RULES.iter().enumerate() {
+                        *by_reason.entry(*reason).or_insert(0) += self.matches(index);
+                    }
+                    by_reason
+                }
+
+                /// Whether the rule at `index` is compiled in, rather than disabled by `#[cfg]`.
+                pub fn enabled(&self, index: usize) -> bool {
+                    self.probes[index].is_some()
+                }
+
+                /// Indices of the enabled rules no recorded request has matched.
+                pub fn uncovered(&self) -> Vec<usize> {
+                    (0..Self::RULES.len())
+                        .filter(|&index| self.enabled(index) && self.matches(index) == 0)
+                        .collect()
+                }
+
+                /// One line per rule with its location, reason code and match count.
+                pub fn report(&self) -> String {
+                    let enabled = (0..Self::RULES.len())
+                        .filter(|&index| self.enabled(index))
+                        .count();
+                    let covered = enabled - self.uncovered().len();
+                    let mut report = format!(
+                        "rule coverage: {} of {} rules matched",
+                        covered,
+                        enabled
+                    );
+                    for (index, (file, line, reason, text)) in Self::RULES.iter().enumerate() {
+                        let matches = if self.enabled(index) {
+                            format!("{} matches", self.matches(index))
+                        } else {
+                            String::from("disabled")
+                        };
+                        report.push_str(&format!(
+                            "\n  {}:{} [{}] {}: {}\n    {}",
+                            file,
+                            line,
+                            index,
+                            reason,
+                            matches,
+                            text
+                        ));
+                    }
+                    report
+                }
+
+                /// Panics listing every rule that no recorded request has matched.
+                pub fn assert_covered(&self) {
+                    let uncovered = self.uncovered();
+                    if !uncovered.is_empty() {
+                        let mut message = String::from("rules never matched:");
+                        for index in uncovered {
+                            let (file, line, _, text) = Self::RULES[index];
+                            message.push_str(&format!("\n  {}:{}: {}", file, line, text));
+                        }
+                        panic!("{}", message);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl PolicyCatalog {
+    pub fn expand(&self) -> TokenStream2 {
+        Self::expand_static(&self.vis, &self.name, &self.policy)
+    }
+
+    /// Expands to the static holding the metadata of the rules of `policy`.
+    pub fn expand_static(
+        vis: &syn::Visibility,
+        name: &Ident,
+        policy: &PolicyDefinition,
+    ) -> TokenStream2 {
+        let entries = policy.rules.iter().enumerate().map(|(index, rule)| {
+            let line = rule.line() as u32;
+            let text = rule.to_string();
+            let reason = rule.reason_code.to_string();
+            let doc = rule.description();
+            let predicates = &rule.cfg;
+            let attributes = rule.attributes.iter().map(|attribute| {
+                let name = attribute.name.to_string();
+                let value = &attribute.value;
+                quote! { (#name, #value) }
+            });
+            quote! {
+                ::gate0_dsl_core::meta::RuleMeta {
+                    index: #index,
+                    line: #line,
+                    rule: #text,
+                    reason: #reason,
+                    doc: #doc,
+                    attributes: &[#(#attributes),*],
+                    enabled: cfg!(all(#(#predicates),*)),
+                }
+            }
+        });
+
+        quote! {
+            #vis static #name: &[::gate0_dsl_core::meta::RuleMeta] = &[#(#entries),*];
+        }
+    }
+}
+
+impl PolicyExplain {
+    pub fn expand(&self) -> TokenStream2 {
+        Self::expand_function(&self.vis, &self.name, &self.policy, self.policy.expand())
+    }
+
+    /// Expands to the function explaining the decisions of `policy`, built once from
+    /// `builder` on the first call.
+    pub fn expand_function(
+        vis: &syn::Visibility,
+        name: &Ident,
+        policy: &PolicyDefinition,
+        builder: TokenStream2,
+    ) -> TokenStream2 {
+        let rules = policy.rules.iter().map(|rule| {
+            let explanation = rule.expand_explanation();
+            rule.expand_gated(quote! {
+                rules.push(#explanation);
+            })
+        });
+
+        quote! {
+            #vis fn #name(request: &::gate0::Request) -> ::gate0_dsl_core::explain::Explanation {
+                fn builder() -> ::gate0::PolicyBuilder {
+                    #builder
+                }
+                static POLICY: ::std::sync::LazyLock<
+                    Result<::gate0::Policy, ::gate0::PolicyError>,
+                > = ::std::sync::LazyLock::new(|| builder().build());
+
+                #[allow(unused_mut)]
+                let mut rules = Vec::new();
+                #(#rules)*
+                ::gate0_dsl_core::explain::Explanation {
+                    decision: match &*POLICY {
+                        Ok(policy) => policy.evaluate(request),
+                        // The error can't be shared, so a failing build is repeated.
+                        Err(_) => builder().build().and_then(|policy| policy.evaluate(request)),
+                    },
+                    rules,
+                }
+            }
+        }
+    }
+}
+
+impl PolicyFn {
+    /// Expands to a function taking the parameters and returning the builder. `$name`
+    /// references expand to calls of the `IntoValue` trait declared inside it, which
+    /// turns string slices, booleans and integers into a `gate0::Value`.
+    pub fn expand(&self) -> TokenStream2 {
+        let vis = &self.vis;
+        let name = &self.name;
+        let names = self.policy.params.iter().map(|param| &param.name);
+        let types = self.policy.params.iter().map(|param| &param.ty);
+        let ints = [
+            quote! { i8 },
+            quote! { i16 },
+            quote! { i32 },
+            quote! { i64 },
+            quote! { u8 },
+            quote! { u16 },
+            quote! { u32 },
+        ];
+        let policy = self.policy.expand();
+        let expectations = self.expand_expectations();
+        let companions = self
+            .companions
+            .iter()
+            .map(|companion| companion.expand(name, &self.policy));
+
+        quote! {
+            #expectations
+            #(#companions)*
+
+            #vis fn #name(#(#names: #types),*) -> ::gate0::PolicyBuilder {
+                trait IntoValue {
+                    fn into_value(self) -> ::gate0::Value<'static>;
+                }
+                impl IntoValue for &'static str {
+                    fn into_value(self) -> ::gate0::Value<'static> {
+                        ::gate0::Value::String(self)
+                    }
+                }
+                impl IntoValue for bool {
+                    fn into_value(self) -> ::gate0::Value<'static> {
+                        ::gate0::Value::Bool(self)
+                    }
+                }
+                #(
+                    impl IntoValue for #ints {
+                        fn into_value(self) -> ::gate0::Value<'static> {
+                            ::gate0::Value::Int(self.into())
+                        }
+                    }
+                )*
+                #policy
+            }
+        }
+    }
+
+    /// Expands to a `#[test]` named `<name>_expectations` that builds the policy once
+    /// with the function and checks every `EXPECT` line against it, panicking with
+    /// all the failing ones. Only available without `PARAMS`.
+    pub fn expand_expectations(&self) -> TokenStream2 {
+        if self.policy.expectations.is_empty() {
+            return quote! {};
+        }
+
+        let name = &self.name;
+        let test_name = Ident::new(&format!("{name}_expectations"), name.span());
+        let checks = self
+            .policy
+            .expectations
+            .iter()
+            .map(|expectation| expectation.expand(&self.policy));
+
+        quote! {
+            #[cfg(test)]
+            #[test]
+            fn #test_name() {
+                struct Mismatch {
+                    expectation: &'static str,
+                    expected: String,
+                    actual: String,
+                    matching_rules: &'static str,
+                }
+
+                let policy = match #name().build() {
+                    Ok(policy) => policy,
+                    Err(err) => panic!("policy expectations failed: the policy doesn't build: {err:?}"),
+                };
+
+                let mut mismatches = Vec::new();
+                #(#checks)*
+
+                if !mismatches.is_empty() {
+                    let mut message = String::from("policy expectations failed:");
+                    for mismatch in &mismatches {
+                        message.push_str(&format!(
+                            "\n  {}\n    expected: {}\n    actual:   {}{}",
+                            mismatch.expectation,
+                            mismatch.expected,
+                            mismatch.actual,
+                            mismatch.matching_rules,
+                        ));
+                    }
+                    panic!("{}", message);
+                }
+            }
+        }
+    }
+}
+
+impl Companion {
+    /// Expands to the item declared in `policy_fn!` next to the function `function`,
+    /// which the items needing the policy's builder call for it.
+    pub fn expand(&self, function: &Ident, policy: &PolicyDefinition) -> TokenStream2 {
+        let vis = &self.vis;
+        let name = &self.name;
+        let builder = quote! { #function() };
+        match self.kind {
+            CompanionKind::Catalog => PolicyCatalog::expand_static(vis, name, policy),
+            CompanionKind::Doc => {
+                let markdown = policy.expand_doc();
+                quote! { #vis const #name: &str = #markdown; }
+            }
+            CompanionKind::Coverage { ref type_name } => {
+                PolicyCoverage::expand_tracker(vis, name, type_name, policy, builder)
+            }
+            CompanionKind::Explain => PolicyExplain::expand_function(vis, name, policy, builder),
+        }
+    }
+}
+
+impl AssertDecision {
+    pub fn expand(&self) -> TokenStream2 {
+        let policy = &self.policy;
+        let check = DecisionCheck {
+            request: &self.request,
+            expected: &self.expected,
+            policy: inline_policy(policy),
+        }
+        .expand(&quote! { policy });
+
+        quote! {
+            {
+                let policy = &(#policy);
+                #check
+            }
+        }
+    }
+}
+
+impl ToTokens for ConfigField {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let name = &self.name;
+        let value = &self.value;
+        tokens.extend(quote! { #name: #value, });
+    }
+}
+
+impl RuleDefinition {
+    pub fn expand(&self) -> TokenStream2 {
+        let effect = self.effect.expand();
+        let target = self.target.expand();
+        let condition = if let Some(ref cond) = self.condition {
+            let cond_expr = cond.expand();
+            quote! { Some(#cond_expr) }
+        } else {
+            quote! { None }
+        };
+        let reason = self.reason_code.expand();
+
+        quote! {
+            ::gate0::Rule::new(
+                #effect,
+                #target,
+                #condition,
+                #reason
+            )
+        }
+    }
+
+    /// Wraps `statements` in a block gated by the rule's `#[cfg(...)]` predicates, or
+    /// returns them as they are when the rule is unconditional.
+    pub fn expand_gated(&self, statements: TokenStream2) -> TokenStream2 {
+        if self.cfg.is_empty() {
+            return statements;
+        }
+        let predicates = &self.cfg;
+        quote! {
+            #[cfg(all(#(#predicates),*))]
+            {
+                #statements
+            }
+        }
+    }
+
+    /// Expands to the rule with its effect replaced by `Allow`, so a policy holding
+    /// only this rule allows exactly the requests the rule matches.
+    pub fn expand_probe(&self) -> TokenStream2 {
+        let target = self.target.expand();
+        let condition = if let Some(ref cond) = self.condition {
+            let cond_expr = cond.expand();
+            quote! { Some(#cond_expr) }
+        } else {
+            quote! { None }
+        };
+        let reason = self.reason_code.expand();
+
+        quote! {
+            ::gate0::Rule::new(
+                ::gate0::Effect::Allow,
+                #target,
+                #condition,
+                #reason
+            )
+        }
+    }
+
+    /// Evaluates the target fields and the `WHERE` tree of this rule for `request`
+    /// into a `gate0_dsl_core::explain::RuleExplanation`.
+    pub fn expand_explanation(&self) -> TokenStream2 {
+        let line = self.line() as u32;
+        let text = self.to_string();
+        let (principal, action, resource) = match self.target {
+            TargetSpec::Any(_) => (None, None, None),
+            TargetSpec::Tuple(ref t) => (Some(&t.principal), Some(&t.action), Some(&t.resource)),
+            TargetSpec::Struct(ref s) => {
+                (s.field("principal"), s.field("action"), s.field("resource"))
+            }
+        };
+        let fields = [
+            ("principal", principal),
+            ("action", action),
+            ("resource", resource),
+        ]
+        .map(|(field, value)| {
+            let (text, matcher) = match value {
+                Some(FieldValue::Any(_)) | None => {
+                    (String::from("*"), quote! { ::gate0::Matcher::Any })
+                }
+                Some(value) => (value.to_string(), value.to_matcher()),
+            };
+            quote! {
+                ::gate0_dsl_core::explain::FieldMatch::evaluate(#field, #text, #matcher, request)
+            }
+        });
+        let condition = match self.condition {
+            Some(ref condition) => {
+                let step = condition.expr.expand_step();
+                quote! { Some(#step) }
+            }
+            None => quote! { None },
+        };
+
+        quote! {
+            ::gate0_dsl_core::explain::RuleExplanation::new(
+                #line,
+                #text,
+                [#(#fields),*],
+                #condition,
+            )
+        }
+    }
+}
+
+impl Effect {
+    pub fn expand(&self) -> TokenStream2 {
+        match self {
+            Effect::Allow => quote! { ::gate0::Effect::Allow },
+            Effect::Deny => quote! { ::gate0::Effect::Deny },
+        }
+    }
+}
+
+impl TargetSpec {
+    pub fn expand(&self) -> TokenStream2 {
+        match self {
+            TargetSpec::Any(_) => quote! { ::gate0::Target::any() },
+            TargetSpec::Tuple(t) => t.expand(),
+            TargetSpec::Struct(s) => s.expand(),
+        }
+    }
+}
+
+impl TupleTarget {
+    pub fn expand(&self) -> TokenStream2 {
+        let principal = self.principal.to_matcher();
+        let action = self.action.to_matcher();
+        let resource = self.resource.to_matcher();
+
+        quote! {
+            ::gate0::Target {
+                principal: #principal,
+                action: #action,
+                resource: #resource,
+            }
+        }
+    }
+}
+
+impl StructTarget {
+    pub fn expand(&self) -> TokenStream2 {
+        let mut principal = None;
+        let mut action = None;
+        let mut resource = None;
+
+        for field in &self.fields {
+            match field.name.to_string().as_str() {
+                "principal" => principal = Some(&field.value),
+                "action" => action = Some(&field.value),
+                "resource" => resource = Some(&field.value),
+                _ => {}
+            }
+        }
+
+        let principal_matcher =
+            principal.map_or_else(|| quote! { ::gate0::Matcher::Any }, FieldValue::to_matcher);
+        let action_matcher =
+            action.map_or_else(|| quote! { ::gate0::Matcher::Any }, FieldValue::to_matcher);
+        let resource_matcher =
+            resource.map_or_else(|| quote! { ::gate0::Matcher::Any }, FieldValue::to_matcher);
+
+        quote! {
+            ::gate0::Target {
+                principal: #principal_matcher,
+                action: #action_matcher,
+                resource: #resource_matcher,
+            }
+        }
+    }
+}
+
+impl FieldValue {
+    pub fn to_matcher(&self) -> TokenStream2 {
+        match self {
+            FieldValue::Literal(lit) => quote! { ::gate0::Matcher::Exact(#lit) },
+            FieldValue::Array(arr) => quote! { ::gate0::Matcher::OneOf(&[#(#arr),*]) },
+            FieldValue::Any(_) => quote! { ::gate0::Matcher::Any },
+        }
+    }
+}
+
+impl ReasonCode {
+    pub fn expand(&self) -> TokenStream2 {
+        match self {
+            ReasonCode::Literal(lit) => quote! { ::gate0::ReasonCode(#lit) },
+            ReasonCode::Ident(ident) => quote! { #ident },
+        }
+    }
+}
+
+impl ConditionExpr {
+    pub fn expand(&self) -> TokenStream2 {
+        self.expr.expand()
+    }
+}
+
+impl Value {
+    pub fn expand(&self) -> TokenStream2 {
+        match self {
+            Value::Bool(value) => {
+                quote! {
+                    ::gate0::Value::Bool(#value)
+                }
+            }
+            Value::Str(value) => {
+                quote! {
+                    ::gate0::Value::String(#value)
+                }
+            }
+            Value::Int(value) => {
+                quote! {
+                    ::gate0::Value::Int(#value.into())
+                }
+            }
+            Value::Param(name) => {
+                quote! {
+                    IntoValue::into_value(#name)
+                }
+            }
+        }
+    }
+}
+
+impl Condition {
+    /// Evaluates this node and its operands for `request` into a
+    /// `gate0_dsl_core::explain::Step`.
+    pub fn expand_step(&self) -> TokenStream2 {
+        let text = self.to_string();
+        let built = self.expand();
+        let (attribute, operands) = match self {
+            Condition::Equals { attr, .. } | Condition::NotEquals { attr, .. } => {
+                (quote! { Some(#attr) }, Vec::new())
+            }
+            Condition::And(left, right) | Condition::Or(left, right) => (
+                quote! { None },
+                vec![left.expand_step(), right.expand_step()],
+            ),
+            Condition::Not(inner) => (quote! { None }, vec![inner.expand_step()]),
+            Condition::AtLeast { attr, .. } => (quote! { Some(#attr) }, Vec::new()),
+            Condition::True | Condition::False => (quote! { None }, Vec::new()),
+        };
+
+        quote! {
+            ::gate0_dsl_core::explain::Step::evaluate(
+                #text,
+                #built,
+                #attribute,
+                vec![#(#operands),*],
+                request,
+            )
+        }
+    }
+
+    pub fn expand(&self) -> TokenStream2 {
+        match self {
+            Condition::Equals { attr, value } => {
+                let attr_str = attr.clone();
+                let value = value.expand();
+                quote! {
+                    ::gate0::Condition::Equals {
+                        attr: #attr_str,
+                        value: #value,
+                    }
+                }
+            }
+            Condition::NotEquals { attr, value } => {
+                let attr_str = attr.clone();
+                let value = value.expand();
+                quote! {
+                    ::gate0::Condition::Not(Box::new(::gate0::Condition::Equals {
+                        attr: #attr_str,
+                        value: #value,
+                    }))
+                }
+            }
+            Condition::And(left, right) => {
+                let left_expr = left.expand();
+                let right_expr = right.expand();
+                quote! {
+                    ::gate0::Condition::And(
+                        Box::new(#left_expr),
+                        Box::new(#right_expr)
+                    )
+                }
+            }
+            Condition::Or(left, right) => {
+                let left_expr = left.expand();
+                let right_expr = right.expand();
+                quote! {
+                    ::gate0::Condition::Or(
+                        Box::new(#left_expr),
+                        Box::new(#right_expr)
+                    )
+                }
+            }
+            Condition::Not(inner) => {
+                let inner_expr = inner.expand();
+                quote! {
+                    ::gate0::Condition::Not(Box::new(#inner_expr))
+                }
+            }
+            Condition::AtLeast { attr, roles, .. } => Condition::any_of(attr, roles).expand(),
+            Condition::True => quote! { ::gate0::Condition::True },
+            Condition::False => quote! { ::gate0::Condition::False },
+        }
+    }
+}
+
+impl IncludePolicy {
+    /// Expands to the `policy_builder!` expansion of the file, together with an
+    /// `include_bytes!` of it so cargo rebuilds the including crate when it changes.
+    ///
+    /// Errors are reported at the path literal, prefixed with the path, and with the
+    /// line and column inside the file where the spans of the parsed tokens carry them.
+    pub fn expand(&self) -> TokenStream2 {
+        self.try_expand()
+            .unwrap_or_else(syn::Error::into_compile_error)
+    }
+
+    fn try_expand(&self) -> syn::Result<TokenStream2> {
+        let relative = self.path.value();
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+            .map_err(|_| syn::Error::new(self.path.span(), "CARGO_MANIFEST_DIR is not set"))?;
+        let path = std::path::Path::new(&manifest_dir).join(&relative);
+        let source = std::fs::read_to_string(&path).map_err(|err| {
+            syn::Error::new(
+                self.path.span(),
+                format!("couldn't read {}: {err}", path.display()),
+            )
+        })?;
+        let tracked = path.to_str().ok_or_else(|| {
+            syn::Error::new(self.path.span(), "the policy path isn't valid UTF-8")
+        })?;
+        let rebuild = quote! {
+            const _: &[u8] = include_bytes!(#tracked);
+        };
+
+        let has_locations = spans_have_locations();
+        let errors = validate_source(&source)
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| {
+                let message = if has_locations {
+                    format!(
+                        "{relative}:{}:{}: {}",
+                        diagnostic.line, diagnostic.column, diagnostic.message
+                    )
+                } else {
+                    format!("{relative}: {}", diagnostic.message)
+                };
+                syn::Error::new(self.path.span(), message)
+            })
+            .reduce(|mut combined, err| {
+                combined.combine(err);
+                combined
+            });
+        if let Some(errors) = errors {
+            let errors = errors.into_compile_error();
+            return Ok(quote! {
+                {
+                    #rebuild
+                    #errors
+                }
+            });
+        }
+
+        let policy: PolicyDefinition = syn::parse_str(&source)?;
+        let expanded = policy.expand_checked();
+        Ok(quote! {
+            {
+                #rebuild
+                #expanded
+            }
+        })
+    }
+}
+
+/// Whether tokens parsed from a string have spans pointing into it. Inside a proc
+/// macro they all point at the macro call instead, so they carry no location in the
+/// string.
+fn spans_have_locations() -> bool {
+    " located"
+        .parse::<TokenStream2>()
+        .ok()
+        .and_then(|tokens| tokens.into_iter().next())
+        .is_some_and(|token| token.span().byte_range() == (1..8))
+}
@@ -0,0 +1,234 @@
+//! The [`Explanation`] returned by functions declared with `policy_explain!`.
+//!
+//! Every target field and every node of a `WHERE` tree is checked by evaluating it
+//! with gate0 as a rule of its own, so an explanation agrees with what
+//! `Policy::evaluate` does for the same request.
+
+/// Why a policy made its decision for one request: every rule in definition order,
+/// with how its target and condition matched.
+#[derive(Debug)]
+pub struct Explanation {
+    /// The decision of the whole policy, or the error building or evaluating it.
+    pub decision: Result<gate0::Decision, gate0::PolicyError>,
+    pub rules: Vec<RuleExplanation>,
+}
+
+#[derive(Debug)]
+pub struct RuleExplanation {
+    /// The line the rule starts on.
+    pub line: u32,
+    /// The rule in DSL syntax.
+    pub rule: &'static str,
+    /// The principal, action and resource matches, in that order.
+    pub fields: [FieldMatch; 3],
+    pub condition: Option<Step>,
+    /// Whether the target and the condition both match, so the rule takes part in
+    /// the decision.
+    pub applies: bool,
+}
+
+#[derive(Debug)]
+pub struct FieldMatch {
+    pub field: &'static str,
+    /// The matcher in DSL syntax, `*` when the rule doesn't restrict the field.
+    pub matcher: &'static str,
+    /// The value of the field in the request.
+    pub actual: String,
+    pub matched: bool,
+}
+
+/// One node of an evaluated `WHERE` tree.
+#[derive(Debug)]
+pub struct Step {
+    /// The condition in DSL syntax.
+    pub condition: &'static str,
+    /// For `EQ` and `NEQ`, the attribute compared and its value in the request in
+    /// DSL syntax, or `None` when the request doesn't carry it.
+    pub attribute: Option<(&'static str, Option<String>)>,
+    pub result: bool,
+    /// The operands of `AND`, `OR` and `NOT`.
+    pub steps: Vec<Step>,
+}
+
+impl RuleExplanation {
+    #[doc(hidden)]
+    pub fn new(
+        line: u32,
+        rule: &'static str,
+        fields: [FieldMatch; 3],
+        condition: Option<Step>,
+    ) -> Self {
+        let applies = fields.iter().all(|field| field.matched)
+            && condition.as_ref().is_none_or(|step| step.result);
+        RuleExplanation {
+            line,
+            rule,
+            fields,
+            condition,
+            applies,
+        }
+    }
+}
+
+impl FieldMatch {
+    #[doc(hidden)]
+    pub fn evaluate(
+        field: &'static str,
+        matcher: &'static str,
+        built: gate0::Matcher,
+        request: &gate0::Request,
+    ) -> Self {
+        let mut target = gate0::Target::any();
+        let actual = match field {
+            "principal" => {
+                target.principal = built;
+                request.principal
+            }
+            "action" => {
+                target.action = built;
+                request.action
+            }
+            _ => {
+                target.resource = built;
+                request.resource
+            }
+        };
+        FieldMatch {
+            field,
+            matcher,
+            actual: actual.to_string(),
+            matched: probe(target, None, request),
+        }
+    }
+}
+
+impl Step {
+    #[doc(hidden)]
+    pub fn evaluate(
+        condition: &'static str,
+        built: gate0::Condition,
+        attribute: Option<&'static str>,
+        steps: Vec<Step>,
+        request: &gate0::Request,
+    ) -> Self {
+        let attribute = attribute.map(|name| {
+            let actual = request
+                .context
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| render(value));
+            (name, actual)
+        });
+        Step {
+            condition,
+            attribute,
+            result: probe(gate0::Target::any(), Some(built), request),
+            steps,
+        }
+    }
+
+    fn write(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        indent: usize,
+        prefix: &str,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "{:indent$}{prefix}{}: {}",
+            "", self.condition, self.result
+        )?;
+        match self.attribute {
+            Some((name, Some(ref actual))) => writeln!(f, " ({name} is {actual})")?,
+            Some((name, None)) => writeln!(f, " ({name} is missing)")?,
+            None => writeln!(f)?,
+        }
+        for step in &self.steps {
+            step.write(f, indent + 2, "")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a single `ALLOW` rule with `target` and `condition` matches `request`.
+///
+/// The limits are lifted so the probe neither fails to build nor refuses the request
+/// where the policy itself was configured with higher limits.
+fn probe(
+    target: gate0::Target,
+    condition: Option<gate0::Condition>,
+    request: &gate0::Request,
+) -> bool {
+    let config = gate0::PolicyConfig {
+        max_rules: usize::MAX,
+        max_condition_depth: usize::MAX,
+        max_context_attrs: usize::MAX,
+        max_matcher_options: usize::MAX,
+        max_string_len: usize::MAX,
+    };
+    let rule = gate0::Rule::new(
+        gate0::Effect::Allow,
+        target,
+        condition,
+        gate0::ReasonCode(1),
+    );
+    gate0::Policy::builder()
+        .config(config)
+        .rule(rule)
+        .build()
+        .and_then(|policy| policy.evaluate(request))
+        .is_ok_and(|decision| decision.is_allow())
+}
+
+fn render(value: &gate0::Value) -> String {
+    match value {
+        gate0::Value::Bool(value) => value.to_string(),
+        gate0::Value::Int(value) => value.to_string(),
+        gate0::Value::String(value) => format!("{value:?}"),
+    }
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.decision {
+            Ok(ref decision) => {
+                let effect = match decision.effect {
+                    gate0::Effect::Allow => "ALLOW",
+                    gate0::Effect::Deny => "DENY",
+                };
+                writeln!(f, "decision: {effect} {}", decision.reason.0)?;
+            }
+            Err(ref err) => writeln!(f, "decision: {err:?}")?,
+        }
+        for rule in &self.rules {
+            write!(f, "{rule}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for RuleExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "line {}: {}", self.line, self.rule)?;
+        for field in &self.fields {
+            let matches = if field.matched {
+                "matches"
+            } else {
+                "doesn't match"
+            };
+            writeln!(
+                f,
+                "  {}: {} {matches} {:?}",
+                field.field, field.matcher, field.actual
+            )?;
+        }
+        if let Some(ref step) = self.condition {
+            step.write(f, 2, "WHERE ")?;
+        }
+        if self.applies {
+            writeln!(f, "  applies")
+        } else {
+            writeln!(f, "  doesn't apply")
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! The grammar behind [gate0_dsl](https://github.com/hardliner66/gate0_dsl), as a regular library.
+//!
+//! The [`ast`] types parse from any token stream via [`syn::parse2`] or [`syn::parse_str`],
+//! [`validate`] checks a parsed policy against its limits, and [`codegen`] turns it into the
+//! tokens the `gate0_dsl` macros expand to. [`source`] finds the macro invocations in Rust
+//! files. Linters, formatters and runtime loaders can share the grammar with the macros
+//! through this crate.
+//!
+//! With the `runtime` feature, `runtime::parse_policy` loads a policy from a string into a
+//! `gate0::PolicyBuilder` without going through the macros. The `explain` feature adds
+//! `explain::Explanation`, which the functions declared with `policy_explain!` return.
+//! [`meta::RuleMeta`] describes the entries of the catalogs declared with `policy_catalog!`.
+//!
+//! ```
+//! use gate0_dsl_core::{ast::PolicyDefinition, validate::validate};
+//!
+//! let policy: PolicyDefinition = syn::parse_str(r#"
+//!     ALLOW ANY WHERE { role EQ "admin" } => ADMIN_ACCESS;
+//!     DENY ("eve" * *) => 3;
+//! "#).unwrap();
+//!
+//! assert_eq!(policy.rules.len(), 2);
+//! assert_eq!(policy.rules[1].to_string(), r#"DENY ("eve" * *) => 3;"#);
+//! assert!(validate(&policy).is_empty());
+//! ```
+
+pub mod ast;
+pub mod codegen;
+#[cfg(feature = "explain")]
+pub mod explain;
+pub mod meta;
+mod parse;
+#[cfg(feature = "runtime")]
+pub mod runtime;
+pub mod source;
+pub mod validate;
@@ -0,0 +1,46 @@
+//! The [`RuleMeta`] entries of the catalogs declared with `policy_catalog!`.
+
+/// What is written about a rule besides its logic: its doc comments and its
+/// `#[name = "value"]` attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleMeta {
+    /// The position of the rule in the policy, counting from 0.
+    pub index: usize,
+    /// The line the rule starts on, after its doc comments and attributes.
+    pub line: u32,
+    /// The rule in DSL syntax.
+    pub rule: &'static str,
+    /// The reason code as written, a constant name or an integer.
+    pub reason: &'static str,
+    /// The doc comments joined into one paragraph, empty when there are none.
+    pub doc: &'static str,
+    /// The attributes as name and value, in the order written.
+    pub attributes: &'static [(&'static str, &'static str)],
+    /// Whether the rule is compiled in, rather than disabled by `#[cfg(...)]`.
+    pub enabled: bool,
+}
+
+impl RuleMeta {
+    /// The value of the attribute `name`.
+    pub fn attribute(&self, name: &str) -> Option<&'static str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| *attribute == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// The value of `#[id = ".."]`.
+    pub fn id(&self) -> Option<&'static str> {
+        self.attribute("id")
+    }
+
+    /// The value of `#[owner = ".."]`.
+    pub fn owner(&self) -> Option<&'static str> {
+        self.attribute("owner")
+    }
+}
+
+/// The rule with the given `#[id = ".."]` in `catalog`.
+pub fn find(catalog: &'static [RuleMeta], id: &str) -> Option<&'static RuleMeta> {
+    catalog.iter().find(|meta| meta.id() == Some(id))
+}
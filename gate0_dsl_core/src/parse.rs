@@ -0,0 +1,1757 @@
+//! `syn` parsers for the DSL, usable from proc macros through `parse_macro_input!`
+//! and at runtime through `syn::parse_str`.
+
+use proc_macro2::{Group, Literal, Span, TokenStream, TokenTree};
+use syn::{
+    Expr, Ident, LitBool, LitInt, LitStr, Result, Token,
+    parse::{Parse, ParseStream, Parser},
+    token::{Brace, Paren},
+};
+
+use crate::ast::*;
+
+const VALID_INT_TYPES_TEXT: &str = "&str, bool, i8, i16, i32, i64, u8, u16, u32";
+
+impl Parse for CtxDefinition {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut pairs = Vec::new();
+
+        while !input.is_empty() {
+            let key: syn::LitStr = input.parse()?;
+            input.parse::<Token![=>]>()?;
+            let value: Value = input.parse()?;
+            pairs.push((key, value));
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(CtxDefinition { pairs })
+    }
+}
+
+/// The keywords of the statements that can come before the rules.
+const HEADERS: [&str; 5] = ["PARAMS", "USE", "CONFIG", "ROLES", "COMBINE"];
+
+impl Parse for PolicyDefinition {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut params = Vec::new();
+        let mut config = None;
+        let mut use_builder = None;
+        let mut roles = None;
+        let mut first_match = false;
+        let mut rules = Vec::new();
+        let mut expectations = Vec::new();
+
+        // The headers come before the rules, in any order, each at most once.
+        let mut headers: Vec<Ident> = Vec::new();
+        while input.peek(Ident) {
+            let ident = input.fork().parse::<Ident>()?;
+            if !HEADERS.iter().any(|header| ident == header) {
+                break;
+            }
+            if headers.contains(&ident) {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("{ident} is already given above"),
+                ));
+            }
+            let _: Ident = input.parse()?;
+            match ident.to_string().as_str() {
+                "PARAMS" => {
+                    let content;
+                    syn::parenthesized!(content in input);
+                    params = content
+                        .parse_terminated(Param::parse, Token![,])?
+                        .into_iter()
+                        .collect();
+                    input.parse::<Token![;]>()?;
+                }
+                "USE" => {
+                    let builder_expr: Expr = input.parse()?;
+                    input.parse::<Token![;]>()?;
+                    use_builder = Some(builder_expr);
+                }
+                "CONFIG" => config = Some(input.parse()?),
+                "ROLES" => roles = Some(input.parse()?),
+                _ => {
+                    let mode: Ident = input.parse()?;
+                    if mode != "FIRST_MATCH" {
+                        return Err(syn::Error::new(mode.span(), "expected FIRST_MATCH"));
+                    }
+                    input.parse::<Token![;]>()?;
+                    first_match = true;
+                }
+            }
+            headers.push(ident);
+        }
+
+        while !input.is_empty() {
+            let lookahead = input.fork();
+            if let Ok(ident) = lookahead.parse::<Ident>()
+                && ident == "EXPECT"
+            {
+                expectations.push(input.parse()?);
+            } else if let Ok(ident) = input.fork().parse::<Ident>()
+                && HEADERS.iter().any(|header| ident == header)
+            {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("{ident} has to come before the rules"),
+                ));
+            } else {
+                parse_rules(input, &Scope::default(), &mut rules)?;
+            }
+        }
+
+        for rule in &mut rules {
+            if let Some(ref mut condition) = rule.condition {
+                resolve_roles(&mut condition.expr, roles.as_ref())?;
+            }
+        }
+        if first_match {
+            rules = combine_first_match(rules)?;
+        }
+
+        Ok(PolicyDefinition {
+            params,
+            config,
+            roles,
+            first_match,
+            use_builder,
+            rules,
+            expectations,
+        })
+    }
+}
+
+/// What the blocks enclosing a rule add to it.
+#[derive(Clone, Default)]
+struct Scope {
+    /// The `#[cfg(...)]` predicates of the enclosing blocks, outermost first.
+    cfg: Vec<TokenStream>,
+    /// The combined target of the enclosing `FOR` blocks.
+    target: Option<TargetSpec>,
+    /// The guards of the enclosing `WITH` blocks, ANDed together outermost first.
+    guard: Option<Condition>,
+    /// The variables of the enclosing `FOR .. IN` loops.
+    variables: Vec<Ident>,
+}
+
+/// Parses one rule, or a block of rules: a braced group, a `FOR` block with a target
+/// shared by its rules, a `WITH` block with a guard ANDed onto their conditions, a
+/// `FOR .. IN` loop repeating its rules for every value in a list, an `RBAC` table, a
+/// decision `TABLE` or a `MATCH` on an attribute.
+/// Blocks are flattened into the rules inside them, with the `#[cfg(...)]` predicates
+/// of the enclosing blocks added in front of each rule's own, the targets combined
+/// field by field and the guards put in front of each rule's condition.
+fn parse_rules(input: ParseStream, scope: &Scope, rules: &mut Vec<RuleDefinition>) -> Result<()> {
+    let lookahead = input.fork();
+    lookahead.call(syn::Attribute::parse_outer)?;
+    let is_group = lookahead.peek(Brace);
+    let keyword = lookahead.parse::<Ident>().ok().filter(|keyword| {
+        ["FOR", "WITH", "RBAC", "TABLE", "MATCH"]
+            .iter()
+            .any(|name| keyword == name)
+    });
+    let is_loop = keyword.as_ref().is_some_and(|keyword| keyword == "FOR")
+        && lookahead.parse::<Ident>().is_ok()
+        && lookahead
+            .parse::<Ident>()
+            .is_ok_and(|keyword| keyword == "IN");
+    if keyword.is_none() && !is_group {
+        let chain: RuleChain = input.parse()?;
+        for rule in chain.rules() {
+            push_rule(rule, scope, rules)?;
+        }
+        return Ok(());
+    }
+
+    let mut scope = scope.clone();
+    for attr in input.call(syn::Attribute::parse_outer)? {
+        if !attr.path().is_ident("cfg") {
+            let block = match keyword {
+                Some(ref keyword) => format!("a {keyword} block"),
+                None => String::from("a group of rules"),
+            };
+            return Err(syn::Error::new_spanned(
+                attr,
+                format!("expected `#[cfg(...)]` on {block}"),
+            ));
+        }
+        scope.cfg.push(attr.meta.require_list()?.tokens.clone());
+    }
+    match keyword {
+        Some(_) if is_loop => return parse_loop(input, &scope, rules),
+        Some(ref keyword) if keyword == "RBAC" => {
+            let table: RbacTable = input.parse()?;
+            for rule in table.rules() {
+                push_rule(rule, &scope, rules)?;
+            }
+            return Ok(());
+        }
+        Some(ref keyword) if keyword == "TABLE" => {
+            let table: DecisionTable = input.parse()?;
+            for rule in table.rules() {
+                push_rule(rule, &scope, rules)?;
+            }
+            return Ok(());
+        }
+        Some(ref keyword) if keyword == "MATCH" => {
+            let block: MatchBlock = input.parse()?;
+            for rule in block.rules() {
+                push_rule(rule, &scope, rules)?;
+            }
+            return Ok(());
+        }
+        Some(ref keyword) if keyword == "FOR" => {
+            let _: Ident = input.parse()?;
+            let target: TargetSpec = input.parse()?;
+            scope.target = Some(match scope.target {
+                Some(ref outer) => merge_targets(outer, &target)?,
+                None => target,
+            });
+        }
+        Some(_) => {
+            let _: Ident = input.parse()?;
+            let guard: ConditionExpr = input.parse()?;
+            scope.guard = Some(match scope.guard {
+                Some(outer) => Condition::And(Box::new(outer), guard.expr),
+                None => *guard.expr,
+            });
+        }
+        None => {}
+    }
+    let content;
+    syn::braced!(content in input);
+    while !content.is_empty() {
+        parse_rules(&content, &scope, rules)?;
+    }
+    Ok(())
+}
+
+/// Adds a rule with what its enclosing blocks add to it.
+fn push_rule(
+    mut rule: RuleDefinition,
+    scope: &Scope,
+    rules: &mut Vec<RuleDefinition>,
+) -> Result<()> {
+    rule.cfg.splice(0..0, scope.cfg.iter().cloned());
+    match (&scope.target, &rule.target) {
+        (Some(outer), inner) => rule.target = merge_targets(outer, inner)?,
+        (None, TargetSpec::Any(omitted)) if rule.target.is_omitted() => {
+            return Err(syn::Error::new(
+                omitted.span(),
+                "expected ANY, *, tuple, or struct",
+            ));
+        }
+        (None, _) => {}
+    }
+    if let Some(ref guard) = scope.guard {
+        let expr = match rule.condition.take() {
+            Some(condition) => Condition::And(Box::new(guard.clone()), condition.expr),
+            None => guard.clone(),
+        };
+        rule.condition = Some(ConditionExpr {
+            expr: Box::new(expr),
+        });
+    }
+    rules.push(rule);
+    Ok(())
+}
+
+/// Parses a `FOR <variable> IN [<values>] { <rules> }` loop, parsing the rules once per
+/// value with the variable substituted into them.
+fn parse_loop(input: ParseStream, scope: &Scope, rules: &mut Vec<RuleDefinition>) -> Result<()> {
+    let _: Ident = input.parse()?;
+    let variable: Ident = input.parse()?;
+    let _: Ident = input.parse()?;
+    if scope.variables.contains(&variable) {
+        return Err(syn::Error::new(
+            variable.span(),
+            format!("`{variable}` is already the variable of an enclosing loop"),
+        ));
+    }
+    let content;
+    syn::bracketed!(content in input);
+    let values = content.parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+    let content;
+    syn::braced!(content in input);
+    let template: TokenStream = content.parse()?;
+
+    let mut scope = scope.clone();
+    scope.variables.push(variable.clone());
+    for value in &values {
+        let body = substitute(template.clone(), &variable, &value.value());
+        let parser = |input: ParseStream| {
+            while !input.is_empty() {
+                parse_rules(input, &scope, rules)?;
+            }
+            Ok(())
+        };
+        parser.parse2(body)?;
+    }
+    Ok(())
+}
+
+/// Replaces `$<variable>` with `value` inside string literals, and as the value of an
+/// `EQ` or `NEQ`. The replacements keep the spans of the template, so errors in the
+/// generated rules point at it.
+fn substitute(tokens: TokenStream, variable: &Ident, value: &str) -> TokenStream {
+    let trees = tokens.into_iter().collect::<Vec<_>>();
+    let mut substituted: Vec<TokenTree> = Vec::with_capacity(trees.len());
+    let mut index = 0;
+    while index < trees.len() {
+        let is_value = matches!(
+            trees[index..],
+            [TokenTree::Punct(ref dollar), TokenTree::Ident(ref name), ..]
+                if dollar.as_char() == '$' && name == variable
+        ) && matches!(
+            substituted.last(),
+            Some(TokenTree::Ident(op)) if op == "EQ" || op == "NEQ"
+        );
+        if is_value {
+            let mut literal = Literal::string(value);
+            literal.set_span(trees[index].span());
+            substituted.push(TokenTree::Literal(literal));
+            index += 2;
+            continue;
+        }
+
+        let tree = match trees[index] {
+            TokenTree::Group(ref group) => {
+                let stream = substitute(group.stream(), variable, value);
+                let mut replaced = Group::new(group.delimiter(), stream);
+                replaced.set_span(group.span());
+                TokenTree::Group(replaced)
+            }
+            TokenTree::Literal(ref literal) => match syn::Lit::new(literal.clone()) {
+                syn::Lit::Str(lit) => {
+                    let text = interpolate(&lit.value(), variable, value);
+                    TokenTree::Literal(LitStr::new(&text, lit.span()).token())
+                }
+                _ => trees[index].clone(),
+            },
+            ref tree => tree.clone(),
+        };
+        substituted.push(tree);
+        index += 1;
+    }
+    substituted.into_iter().collect()
+}
+
+/// Replaces every `$<variable>` in `text` that isn't the start of a longer name.
+fn interpolate(text: &str, variable: &Ident, value: &str) -> String {
+    let pattern = format!("${variable}");
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(&pattern) {
+        let after = &rest[start + pattern.len()..];
+        result.push_str(&rest[..start]);
+        if after.starts_with(|ch: char| ch.is_alphanumeric() || ch == '_') {
+            result.push_str(&pattern);
+        } else {
+            result.push_str(value);
+        }
+        rest = after;
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Combines the target of a `FOR` block with a target inside it. Each field takes the
+/// inner value where the inner target restricts it and the outer value otherwise. An
+/// inner value has to be within the outer one, so blocks only ever narrow. The fields
+/// keep the spans of the target their value comes from.
+fn merge_targets(outer: &TargetSpec, inner: &TargetSpec) -> Result<TargetSpec> {
+    let mut fields = Vec::new();
+    for &name in StructTargetField::valid_field_names() {
+        let span = match inner.field(name) {
+            Some(_) => inner.field_span(name),
+            None => outer.field_span(name),
+        };
+        let value = match (outer.field(name), inner.field(name)) {
+            (Some(outer), Some(inner)) => {
+                let allowed = outer.literals();
+                let lits = match inner {
+                    FieldValue::Literal(lit) => std::slice::from_ref(lit),
+                    FieldValue::Array(arr) => arr.as_slice(),
+                    FieldValue::Any(_) => &[],
+                };
+                if let Some(lit) = lits.iter().find(|lit| !allowed.contains(&lit.value())) {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        format!(
+                            "{:?} is outside of the FOR block's {name} {outer}",
+                            lit.value()
+                        ),
+                    ));
+                }
+                inner
+            }
+            (Some(value), None) | (None, Some(value)) => value,
+            (None, None) => continue,
+        };
+        fields.push(StructTargetField {
+            name: Ident::new(name, span),
+            value: value.clone(),
+        });
+    }
+
+    if fields.is_empty() {
+        let target = if inner.is_omitted() { outer } else { inner };
+        return Ok(TargetSpec::Any(Ident::new("ANY", target.span())));
+    }
+    Ok(TargetSpec::Struct(StructTarget { fields }))
+}
+
+impl Parse for Expectation {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let keyword: Ident = input.parse()?;
+        if keyword != "EXPECT" {
+            return Err(syn::Error::new(keyword.span(), "expected EXPECT"));
+        }
+        let request: RequestSpec = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let expected: ExpectedDecision = input.parse()?;
+        input.parse::<Token![;]>()?;
+
+        Ok(Expectation { request, expected })
+    }
+}
+
+impl Parse for RequestSpec {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let (principal, action, resource) = if input.peek(Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            (content.parse()?, content.parse()?, content.parse()?)
+        } else {
+            (input.parse()?, input.parse()?, input.parse()?)
+        };
+
+        if input.peek(Ident) {
+            let lookahead = input.fork();
+            if let Ok(ident) = lookahead.parse::<Ident>()
+                && ident == "WITH"
+            {
+                let _: Ident = input.parse()?;
+                if !input.peek(Brace) {
+                    return Err(syn::Error::new(
+                        input.span(),
+                        "expected a context block after WITH",
+                    ));
+                }
+            }
+        }
+
+        let mut pairs = Vec::new();
+        if input.peek(Brace) {
+            let content;
+            syn::braced!(content in input);
+            while !content.is_empty() {
+                let key = if content.peek(Ident) {
+                    let ident: Ident = content.parse()?;
+                    LitStr::new(&ident.to_string(), ident.span())
+                } else {
+                    content.parse()?
+                };
+                content.parse::<Token![=>]>()?;
+                let value: Value = content.parse()?;
+                pairs.push((key, value));
+
+                if !content.is_empty() {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+        }
+
+        Ok(RequestSpec {
+            principal,
+            action,
+            resource,
+            context: CtxDefinition { pairs },
+        })
+    }
+}
+
+impl Parse for ExpectedDecision {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let effect: Effect = input.parse()?;
+        let reason = if input.peek(Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            Some(content.parse()?)
+        } else if input.peek(LitInt) || input.peek(Ident) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(ExpectedDecision { effect, reason })
+    }
+}
+
+impl Parse for PolicyTests {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let keyword: Ident = input.parse()?;
+        if keyword != "POLICY" {
+            return Err(syn::Error::new(keyword.span(), "expected POLICY"));
+        }
+        let policy: Expr = input.parse()?;
+        input.parse::<Token![;]>()?;
+
+        let mut rows = Vec::new();
+        while !input.is_empty() {
+            rows.push(input.parse()?);
+        }
+
+        Ok(PolicyTests { policy, rows })
+    }
+}
+
+impl Parse for TestRow {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = if input.peek(Ident) && input.peek2(Token![:]) {
+            let name: Ident = input.parse()?;
+            input.parse::<Token![:]>()?;
+            Some(name)
+        } else {
+            None
+        };
+        let request: RequestSpec = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let expected: ExpectedDecision = input.parse()?;
+        input.parse::<Token![;]>()?;
+
+        Ok(TestRow {
+            name,
+            request,
+            expected,
+        })
+    }
+}
+
+impl Parse for PolicyCoverage {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let vis: syn::Visibility = input.parse()?;
+        input.parse::<Token![static]>()?;
+        let name: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let type_name: Ident = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let policy: PolicyDefinition = input.parse()?;
+
+        Ok(PolicyCoverage {
+            vis,
+            name,
+            type_name,
+            policy,
+        })
+    }
+}
+
+impl Parse for PolicyCatalog {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let vis: syn::Visibility = input.parse()?;
+        input.parse::<Token![static]>()?;
+        let name: Ident = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let policy: PolicyDefinition = input.parse()?;
+
+        Ok(PolicyCatalog { vis, name, policy })
+    }
+}
+
+impl Parse for PolicyExplain {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let vis: syn::Visibility = input.parse()?;
+        input.parse::<Token![fn]>()?;
+        let name: Ident = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let policy: PolicyDefinition = input.parse()?;
+
+        Ok(PolicyExplain { vis, name, policy })
+    }
+}
+
+impl Parse for PolicyFn {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let vis: syn::Visibility = input.parse()?;
+        input.parse::<Token![fn]>()?;
+        let name: Ident = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let mut companions = Vec::new();
+        while let Ok(keyword) = input.fork().parse::<Ident>()
+            && ["CATALOG", "DOC", "COVERAGE", "EXPLAIN"]
+                .iter()
+                .any(|name| keyword == name)
+        {
+            companions.push(input.parse()?);
+        }
+        let policy: PolicyDefinition = input.parse()?;
+
+        Ok(PolicyFn {
+            vis,
+            name,
+            companions,
+            policy,
+        })
+    }
+}
+
+impl Parse for Companion {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let keyword: Ident = input.parse()?;
+        let vis: syn::Visibility = input.parse()?;
+        let (name, kind) = match keyword.to_string().as_str() {
+            "CATALOG" => {
+                input.parse::<Token![static]>()?;
+                (input.parse()?, CompanionKind::Catalog)
+            }
+            "DOC" => {
+                input.parse::<Token![const]>()?;
+                (input.parse()?, CompanionKind::Doc)
+            }
+            "COVERAGE" => {
+                input.parse::<Token![static]>()?;
+                let name = input.parse()?;
+                input.parse::<Token![:]>()?;
+                let type_name = input.parse()?;
+                (name, CompanionKind::Coverage { type_name })
+            }
+            _ => {
+                input.parse::<Token![fn]>()?;
+                (input.parse()?, CompanionKind::Explain)
+            }
+        };
+        input.parse::<Token![;]>()?;
+
+        Ok(Companion {
+            keyword,
+            vis,
+            name,
+            kind,
+        })
+    }
+}
+
+impl Parse for Param {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty: syn::Type = input.parse()?;
+
+        Ok(Param { name, ty })
+    }
+}
+
+impl Parse for IncludePolicy {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let path: LitStr = input.parse()?;
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(IncludePolicy { path })
+    }
+}
+
+impl Parse for AssertDecision {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let policy: Expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let request: RequestSpec = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let expected: ExpectedDecision = input.parse()?;
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(AssertDecision {
+            policy,
+            request,
+            expected,
+        })
+    }
+}
+
+impl Parse for RbacTable {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let keyword: Ident = input.parse()?;
+        if keyword != "RBAC" {
+            return Err(syn::Error::new(keyword.span(), "expected RBAC"));
+        }
+        let content;
+        syn::braced!(content in input);
+
+        let name: Ident = content.parse()?;
+        if name != "attr" {
+            return Err(syn::Error::new(
+                name.span(),
+                "expected `attr: <attribute>;` to start the table",
+            ));
+        }
+        content.parse::<Token![:]>()?;
+        let attr = parse_role(&content)?;
+        content.parse::<Token![;]>()?;
+
+        let mut reason = None;
+        if content.peek(Ident) && content.peek2(Token![:]) {
+            let name: Ident = content.parse()?;
+            if name != "reason" {
+                return Err(syn::Error::new(name.span(), "expected `reason: <reason>;`"));
+            }
+            content.parse::<Token![:]>()?;
+            reason = Some(content.parse()?);
+            content.parse::<Token![;]>()?;
+        }
+
+        let mut rows: Vec<RbacRow> = Vec::new();
+        while !content.is_empty() {
+            let role = parse_role(&content)?;
+            if rows.iter().any(|row| row.role.value() == role.value()) {
+                return Err(syn::Error::new(
+                    role.span(),
+                    format!("the role {:?} is listed more than once", role.value()),
+                ));
+            }
+            content.parse::<Token![=>]>()?;
+            let actions: FieldValue = content.parse()?;
+            let mut row_reason = None;
+            if content.peek(Token![=>]) {
+                content.parse::<Token![=>]>()?;
+                row_reason = Some(content.parse()?);
+            }
+            content.parse::<Token![;]>()?;
+            rows.push(RbacRow {
+                role,
+                actions,
+                reason: row_reason,
+            });
+        }
+
+        Ok(RbacTable { attr, reason, rows })
+    }
+}
+
+impl Parse for MatchBlock {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let keyword: Ident = input.parse()?;
+        if keyword != "MATCH" {
+            return Err(syn::Error::new(keyword.span(), "expected MATCH"));
+        }
+        let attr = parse_role(input)?;
+        let content;
+        syn::braced!(content in input);
+
+        let mut arms: Vec<MatchArm> = Vec::new();
+        while !content.is_empty() {
+            if arms.iter().any(|arm| arm.value.is_none()) {
+                return Err(syn::Error::new(
+                    content.span(),
+                    "the `_` arm has to be the last one",
+                ));
+            }
+            let value = if content.peek(Token![_]) {
+                content.parse::<Token![_]>()?;
+                None
+            } else {
+                let value: Value = content.parse()?;
+                let is_duplicate = arms.iter().any(|arm| {
+                    arm.value
+                        .as_ref()
+                        .is_some_and(|other| other.to_string() == value.to_string())
+                });
+                if is_duplicate {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        format!("the arm {value} is listed more than once"),
+                    ));
+                }
+                Some(value)
+            };
+            content.parse::<Token![=>]>()?;
+            arms.push(MatchArm {
+                value,
+                chain: content.parse()?,
+            });
+        }
+
+        Ok(MatchBlock { attr, arms })
+    }
+}
+
+impl Parse for DecisionTable {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let keyword: Ident = input.parse()?;
+        if keyword != "TABLE" {
+            return Err(syn::Error::new(keyword.span(), "expected TABLE"));
+        }
+        let complete = input.peek(Ident);
+        if complete {
+            let mode: Ident = input.parse()?;
+            if mode != "COMPLETE" {
+                return Err(syn::Error::new(mode.span(), "expected COMPLETE"));
+            }
+        }
+
+        let content;
+        syn::parenthesized!(content in input);
+        let mut columns: Vec<TableColumn> = Vec::new();
+        while !content.is_empty() {
+            let name = parse_role(&content)?;
+            if columns
+                .iter()
+                .any(|column| column.name.value() == name.value())
+            {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("the column {:?} is listed more than once", name.value()),
+                ));
+            }
+            let mut column = TableColumn { name, values: None };
+            if content.peek(Token![:]) {
+                content.parse::<Token![:]>()?;
+                let list;
+                syn::bracketed!(list in content);
+                let values = list.parse_terminated(<Value as Parse>::parse, Token![,])?;
+                for value in &values {
+                    check_cell_value(&column, value)?;
+                }
+                column.values = Some(values.into_iter().collect());
+            }
+            columns.push(column);
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        }
+        if columns.is_empty() {
+            return Err(syn::Error::new(
+                keyword.span(),
+                "expected at least one column",
+            ));
+        }
+
+        let content;
+        syn::braced!(content in input);
+        let mut rows = Vec::new();
+        while !content.is_empty() {
+            let cells;
+            let paren = syn::parenthesized!(cells in content);
+            let cells = cells.parse_terminated(<TableCell as Parse>::parse, Token![,])?;
+            if cells.len() != columns.len() {
+                return Err(syn::Error::new(
+                    paren.span.open(),
+                    format!(
+                        "the row has {} cells, but the table has {} columns",
+                        cells.len(),
+                        columns.len()
+                    ),
+                ));
+            }
+            for (column, cell) in columns.iter().zip(&cells) {
+                for value in cell.values().unwrap_or_default() {
+                    check_cell_value(column, value)?;
+                    let is_declared = column
+                        .values
+                        .as_ref()
+                        .is_none_or(|values| values.iter().any(|declared| declared.same_as(value)));
+                    if !is_declared {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            format!(
+                                "{value} isn't one of the declared values of {:?}",
+                                column.name.value()
+                            ),
+                        ));
+                    }
+                }
+            }
+            content.parse::<Token![=>]>()?;
+            let effect: Effect = content.parse()?;
+            let reason: ReasonCode = content.parse()?;
+            content.parse::<Token![;]>()?;
+            rows.push(TableRow {
+                span: paren.span.open(),
+                cells: cells.into_iter().collect(),
+                effect,
+                reason,
+            });
+        }
+
+        let table = DecisionTable {
+            keyword,
+            complete,
+            columns,
+            rows,
+        };
+        if !table.complete {
+            return Ok(table);
+        }
+        if table.columns.iter().all(|column| column.values.is_none()) {
+            return Err(syn::Error::new(
+                table.keyword.span(),
+                "TABLE COMPLETE needs a column with declared values",
+            ));
+        }
+        let Some(uncovered) = table.uncovered() else {
+            return Err(syn::Error::new(
+                table.keyword.span(),
+                format!(
+                    "TABLE COMPLETE checks at most {MAX_TABLE_COMBINATIONS} combinations of \
+                     the declared values"
+                ),
+            ));
+        };
+        if !uncovered.is_empty() {
+            let names = table
+                .columns
+                .iter()
+                .filter(|column| column.values.is_some())
+                .map(|column| column.name.value())
+                .collect::<Vec<_>>();
+            let mut combinations = uncovered
+                .iter()
+                .take(5)
+                .map(|combination| {
+                    let comparisons = names
+                        .iter()
+                        .zip(combination)
+                        .map(|(name, value)| format!("{name} EQ {value}"))
+                        .collect::<Vec<_>>();
+                    comparisons.join(" AND ")
+                })
+                .collect::<Vec<_>>();
+            if uncovered.len() > 5 {
+                combinations.push(format!("and {} more", uncovered.len() - 5));
+            }
+            return Err(syn::Error::new(
+                table.keyword.span(),
+                format!("no row covers {}", combinations.join("; ")),
+            ));
+        }
+
+        Ok(table)
+    }
+}
+
+impl Parse for TableCell {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Token![*]) {
+            let star = input.parse::<Token![*]>()?;
+            Ok(TableCell::Any(star.span))
+        } else if input.peek(Ident) {
+            let ident: Ident = input.parse()?;
+            if ident == "ANY" {
+                Ok(TableCell::Any(ident.span()))
+            } else {
+                Err(syn::Error::new(
+                    ident.span(),
+                    "expected ANY, *, a value, or an array",
+                ))
+            }
+        } else if input.peek(syn::token::Bracket) {
+            let content;
+            syn::bracketed!(content in input);
+            let values = content.parse_terminated(<Value as Parse>::parse, Token![,])?;
+            Ok(TableCell::Array(values.into_iter().collect()))
+        } else {
+            Ok(TableCell::Value(input.parse()?))
+        }
+    }
+}
+
+/// Only strings can go into the columns matching a target field.
+fn check_cell_value(column: &TableColumn, value: &Value) -> Result<()> {
+    if column.is_target_field() && !matches!(value, Value::Str(_)) {
+        return Err(syn::Error::new(
+            value.span(),
+            format!("the {:?} column only takes strings", column.name.value()),
+        ));
+    }
+    Ok(())
+}
+
+impl Parse for RoleHierarchy {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        syn::braced!(content in input);
+        if input.peek(Token![;]) {
+            input.parse::<Token![;]>()?;
+        }
+
+        let mut hierarchy = RoleHierarchy { chains: Vec::new() };
+        while !content.is_empty() {
+            hierarchy.chains.push(vec![parse_role(&content)?]);
+            while content.peek(Token![>]) {
+                content.parse::<Token![>]>()?;
+                let lower = parse_role(&content)?;
+                let chain = hierarchy.chains.last().unwrap();
+                let higher = chain.last().unwrap().value();
+                // Putting `lower` below `higher` closes a cycle when `lower` is already
+                // `higher` or above it.
+                let is_cycle = hierarchy
+                    .at_least(&higher)
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|role| role.value() == lower.value());
+                if is_cycle {
+                    return Err(syn::Error::new(
+                        lower.span(),
+                        format!("{:?} would be above itself in ROLES", lower.value()),
+                    ));
+                }
+                hierarchy.chains.last_mut().unwrap().push(lower);
+            }
+
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(hierarchy)
+    }
+}
+
+/// A role in `ROLES` or `RBAC`, written as an identifier or a string literal.
+fn parse_role(input: ParseStream) -> Result<LitStr> {
+    if input.peek(LitStr) {
+        return input.parse();
+    }
+    let ident: Ident = input.parse()?;
+    Ok(LitStr::new(&ident.to_string(), ident.span()))
+}
+
+/// The target fields, in the order [`field_sets`] returns them.
+const TARGET_FIELDS: [&str; 3] = ["principal", "action", "resource"];
+
+/// A part of a rule's target, with the condition it applies under.
+struct Piece {
+    fields: [Option<Vec<String>>; 3],
+    condition: Option<Condition>,
+}
+
+/// The values a target matches for each field, `None` where it matches any.
+fn field_sets(target: &TargetSpec) -> [Option<Vec<String>>; 3] {
+    TARGET_FIELDS.map(|name| target.field(name).map(FieldValue::literals))
+}
+
+/// A rule before the one being split, with the values its target matches and its
+/// condition.
+struct Earlier {
+    span: Span,
+    effect: Effect,
+    fields: [Option<Vec<String>>; 3],
+    condition: Option<Condition>,
+}
+
+/// A field that an earlier rule restricts but the rule being split matches any value
+/// of, which a target can't exclude the earlier rule's values from.
+struct Unsplittable<'a> {
+    earlier: &'a Earlier,
+    field: usize,
+}
+
+/// Splits `piece` along the targets of `earlier`, into the pieces no earlier rule
+/// applies to: the part inside an earlier rule's target gets the negation of that
+/// rule's condition, the parts outside of it keep the piece's own. `None` when no
+/// earlier rule overlaps the piece.
+fn split<'a>(
+    piece: Piece,
+    earlier: impl IntoIterator<Item = &'a Earlier>,
+) -> std::result::Result<Option<Vec<Piece>>, Unsplittable<'a>> {
+    let mut pieces = vec![piece];
+    let mut is_split = false;
+    for earlier in earlier {
+        let mut next = Vec::new();
+        for piece in pieces {
+            let is_disjoint = piece
+                .fields
+                .iter()
+                .zip(&earlier.fields)
+                .any(|pair| match pair {
+                    (Some(own), Some(other)) => !own.iter().any(|value| other.contains(value)),
+                    _ => false,
+                });
+            if is_disjoint {
+                next.push(piece);
+                continue;
+            }
+            is_split = true;
+
+            let mut inside = piece.fields.clone();
+            for (field, other) in earlier.fields.iter().enumerate() {
+                let Some(other) = other else {
+                    continue;
+                };
+                let Some(ref own) = piece.fields[field] else {
+                    return Err(Unsplittable { earlier, field });
+                };
+                let (shared, rest): (Vec<_>, Vec<_>) =
+                    own.iter().cloned().partition(|value| other.contains(value));
+                if !rest.is_empty() {
+                    let mut outside = inside.clone();
+                    outside[field] = Some(rest);
+                    next.push(Piece {
+                        fields: outside,
+                        condition: piece.condition.clone(),
+                    });
+                }
+                inside[field] = Some(shared);
+            }
+            if let Some(ref condition) = earlier.condition {
+                let negation = Condition::Not(Box::new(condition.clone()));
+                next.push(Piece {
+                    fields: inside,
+                    condition: Some(match piece.condition {
+                        Some(own) => Condition::And(Box::new(own), Box::new(negation)),
+                        None => negation,
+                    }),
+                });
+            }
+        }
+        pieces = next;
+    }
+    Ok(is_split.then_some(pieces))
+}
+
+/// Rewrites the rules for `COMBINE FIRST_MATCH;`, so that the first matching rule
+/// decides.
+///
+/// gate0 lets any matching DENY win over the ALLOWs, and the earliest matching rule
+/// win among rules of the same effect. So the ALLOWs are kept as they are, and only
+/// the DENYs are narrowed, to the requests none of the ALLOWs before them apply to.
+/// Specific DENYs followed by a catch-all of either effect need no rewriting at all.
+///
+/// A last DENY that can't be split is dropped instead: it only decides the requests
+/// none of the rules before it match, which gate0 denies without it as well.
+fn combine_first_match(rules: Vec<RuleDefinition>) -> Result<Vec<RuleDefinition>> {
+    if let Some(rule) = rules.iter().find(|rule| !rule.cfg.is_empty()) {
+        return Err(syn::Error::new(
+            rule.span,
+            "#[cfg] isn't supported with COMBINE FIRST_MATCH",
+        ));
+    }
+    let earlier = rules
+        .iter()
+        .map(|rule| Earlier {
+            span: rule.span,
+            effect: rule.effect.clone(),
+            fields: field_sets(&rule.target),
+            condition: rule
+                .condition
+                .as_ref()
+                .map(|condition| (*condition.expr).clone()),
+        })
+        .collect::<Vec<_>>();
+
+    let last = rules.len().saturating_sub(1);
+    let mut combined = Vec::new();
+    for (index, rule) in rules.into_iter().enumerate() {
+        let piece = || Piece {
+            fields: earlier[index].fields.clone(),
+            condition: earlier[index].condition.clone(),
+        };
+        if let Ok(Some(pieces)) = split(piece(), &earlier[..index])
+            && pieces.is_empty()
+        {
+            return Err(syn::Error::new(
+                rule.span,
+                "this rule never applies with COMBINE FIRST_MATCH, the rules before it \
+                 match all of its requests",
+            ));
+        }
+        if matches!(rule.effect, Effect::Allow) {
+            combined.push(rule);
+            continue;
+        }
+
+        let allows = earlier[..index]
+            .iter()
+            .filter(|earlier| matches!(earlier.effect, Effect::Allow));
+        let pieces = match split(piece(), allows) {
+            Ok(Some(pieces)) => pieces,
+            Ok(None) => {
+                combined.push(rule);
+                continue;
+            }
+            Err(Unsplittable { .. }) if index == last => continue,
+            Err(Unsplittable { earlier, field }) => {
+                let name = TARGET_FIELDS[field];
+                let mut err = syn::Error::new(
+                    rule.span,
+                    format!(
+                        "COMBINE FIRST_MATCH can't leave the {name} {:?} of an earlier ALLOW \
+                         out of this DENY's `*`; list the {name} values instead",
+                        earlier.fields[field].as_deref().unwrap_or_default()
+                    ),
+                );
+                err.combine(syn::Error::new(
+                    earlier.span,
+                    format!("this ALLOW restricts the {name}"),
+                ));
+                return Err(err);
+            }
+        };
+        for (index, piece) in pieces.into_iter().enumerate() {
+            let fields = TARGET_FIELDS
+                .iter()
+                .zip(piece.fields)
+                .filter_map(|(name, values)| {
+                    let mut values = values?
+                        .iter()
+                        .map(|value| LitStr::new(value, rule.span))
+                        .collect::<Vec<_>>();
+                    Some(StructTargetField {
+                        name: Ident::new(name, rule.span),
+                        value: match values.len() {
+                            1 => FieldValue::Literal(values.remove(0)),
+                            _ => FieldValue::Array(values),
+                        },
+                    })
+                })
+                .collect::<Vec<_>>();
+            combined.push(RuleDefinition {
+                docs: rule.docs.clone(),
+                // Metadata stays with the first piece, so that ids remain unique.
+                attributes: match index {
+                    0 => rule.attributes.clone(),
+                    _ => Vec::new(),
+                },
+                cfg: Vec::new(),
+                span: rule.span,
+                effect: rule.effect.clone(),
+                target: if fields.is_empty() {
+                    TargetSpec::Any(Ident::new("ANY", rule.span))
+                } else {
+                    TargetSpec::Struct(StructTarget { fields })
+                },
+                condition: piece.condition.map(|expr| ConditionExpr {
+                    expr: Box::new(expr),
+                }),
+                reason_code: rule.reason_code.clone(),
+            });
+        }
+    }
+    Ok(combined)
+}
+
+/// Fills in the roles of every `AT_LEAST` from the `ROLES` declaration.
+fn resolve_roles(condition: &mut Condition, hierarchy: Option<&RoleHierarchy>) -> Result<()> {
+    match condition {
+        Condition::AtLeast { role, roles, .. } => {
+            let Some(hierarchy) = hierarchy else {
+                return Err(syn::Error::new(
+                    role.span(),
+                    "AT_LEAST needs the roles declared with ROLES { .. };",
+                ));
+            };
+            let Some(above) = hierarchy.at_least(&role.value()) else {
+                return Err(syn::Error::new(
+                    role.span(),
+                    format!("{:?} isn't declared in ROLES", role.value()),
+                ));
+            };
+            *roles = above.into_iter().cloned().map(Value::Str).collect();
+        }
+        Condition::And(left, right) | Condition::Or(left, right) => {
+            resolve_roles(left, hierarchy)?;
+            resolve_roles(right, hierarchy)?;
+        }
+        Condition::Not(inner) => resolve_roles(inner, hierarchy)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+impl Parse for ConfigBlock {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        syn::braced!(content in input);
+        input.parse::<Token![;]>()?;
+
+        let mut fields = Vec::new();
+        while !content.is_empty() {
+            let name: Ident = content.parse()?;
+            content.parse::<Token![:]>()?;
+            let value: Expr = content.parse()?;
+            fields.push(ConfigField { name, value });
+
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(ConfigBlock { fields })
+    }
+}
+
+impl Parse for RuleDefinition {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let rule = parse_rule(input)?;
+        input.parse::<Token![;]>()?;
+        Ok(rule)
+    }
+}
+
+impl Parse for RuleChain {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let rule = parse_rule(input)?;
+        let mut branches: Vec<ElseBranch> = Vec::new();
+        while input.peek(Ident) {
+            let keyword: Ident = input.parse()?;
+            if keyword != "ELSE" {
+                return Err(syn::Error::new(keyword.span(), "expected `;` or ELSE"));
+            }
+            let is_unconditional = match branches.last() {
+                Some(branch) => branch.condition.is_none(),
+                None => rule.condition.is_none(),
+            };
+            if is_unconditional {
+                return Err(syn::Error::new(
+                    keyword.span(),
+                    "ELSE can only follow a branch with a condition",
+                ));
+            }
+            let condition = match input.fork().parse::<Ident>() {
+                Ok(ident) if ident == "IF" => {
+                    let _: Ident = input.parse()?;
+                    Some(input.parse()?)
+                }
+                _ => None,
+            };
+            let effect: Effect = input.parse()?;
+            if let Ok(ident) = input.fork().parse::<Ident>()
+                && ident == "WHERE"
+            {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "a branch with a condition is written `ELSE IF { <condition> } <ALLOW|DENY>`",
+                ));
+            }
+            input.parse::<Token![=>]>()?;
+            let reason_code: ReasonCode = input.parse()?;
+            branches.push(ElseBranch {
+                span: keyword.span(),
+                condition,
+                effect,
+                reason_code,
+            });
+        }
+        input.parse::<Token![;]>()?;
+
+        Ok(RuleChain { rule, branches })
+    }
+}
+
+/// Parses a rule up to its reason code, leaving the `;` or the `ELSE` branches after it.
+fn parse_rule(input: ParseStream) -> Result<RuleDefinition> {
+    let mut docs = Vec::new();
+    let mut attributes = Vec::new();
+    let mut cfg = Vec::new();
+    for attr in input.call(syn::Attribute::parse_outer)? {
+        match attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                value:
+                    Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(ref value),
+                        ..
+                    }),
+                ..
+            }) if path.get_ident().is_some() => {
+                if path.is_ident("doc") {
+                    docs.push(value.value());
+                } else {
+                    attributes.push(RuleAttribute {
+                        name: path.get_ident().unwrap().clone(),
+                        value: value.clone(),
+                    });
+                }
+            }
+            syn::Meta::List(ref list) if list.path.is_ident("cfg") => {
+                cfg.push(list.tokens.clone());
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected a doc comment, `#[cfg(...)]` or a `#[name = \"value\"]` attribute",
+                ));
+            }
+        }
+    }
+
+    let span = input.span();
+    let effect: Effect = input.parse()?;
+    let omitted = input.peek(Token![=>])
+        || input
+            .fork()
+            .parse::<Ident>()
+            .is_ok_and(|keyword| keyword == "WHERE");
+    let target = if omitted {
+        TargetSpec::Any(Ident::new("omitted", input.span()))
+    } else {
+        input.parse()?
+    };
+
+    let condition = if input.peek(Ident) {
+        let lookahead = input.fork();
+        if let Ok(ident) = lookahead.parse::<Ident>() {
+            if ident == "WHERE" {
+                let _: Ident = input.parse()?;
+                Some(input.parse()?)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    input.parse::<Token![=>]>()?;
+    let reason_code: ReasonCode = input.parse()?;
+
+    Ok(RuleDefinition {
+        docs,
+        attributes,
+        cfg,
+        span,
+        effect,
+        target,
+        condition,
+        reason_code,
+    })
+}
+
+impl Parse for Effect {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let ident: Ident = input.parse()?;
+        match ident.to_string().as_str() {
+            "ALLOW" => Ok(Effect::Allow),
+            "DENY" => Ok(Effect::Deny),
+            _ => Err(syn::Error::new(ident.span(), "expected ALLOW or DENY")),
+        }
+    }
+}
+
+impl Parse for TargetSpec {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Token![*]) {
+            input.parse::<Token![*]>()?;
+            Ok(TargetSpec::Any(Ident::new("star", Span::call_site())))
+        } else if input.peek(Ident) {
+            let ident: Ident = input.parse()?;
+            if ident == "ANY" {
+                Ok(TargetSpec::Any(ident))
+            } else {
+                Err(syn::Error::new(
+                    ident.span(),
+                    "expected ANY, *, tuple, or struct",
+                ))
+            }
+        } else if input.peek(Paren) {
+            Ok(TargetSpec::Tuple(input.parse()?))
+        } else if input.peek(Brace) {
+            Ok(TargetSpec::Struct(input.parse()?))
+        } else {
+            Err(syn::Error::new(
+                input.span(),
+                "expected *, ANY, tuple, or struct",
+            ))
+        }
+    }
+}
+
+impl Parse for TupleTarget {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        syn::parenthesized!(content in input);
+
+        let principal = content.parse()?;
+        let action = content.parse()?;
+        let resource = content.parse()?;
+
+        Ok(TupleTarget {
+            principal,
+            action,
+            resource,
+        })
+    }
+}
+
+impl Parse for StructTarget {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        syn::braced!(content in input);
+
+        let mut fields = Vec::new();
+        while !content.is_empty() {
+            fields.push(content.parse()?);
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(StructTarget { fields })
+    }
+}
+
+impl Parse for StructTargetField {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        let valid_field_names = Self::valid_field_names();
+        if !valid_field_names.contains(&name.to_string().as_str()) {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("expected one of: {}", valid_field_names.join(", ")),
+            ));
+        }
+        input.parse::<Token![:]>()?;
+        let value: FieldValue = input.parse()?;
+        Ok(StructTargetField { name, value })
+    }
+}
+
+impl Parse for FieldValue {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Token![*]) {
+            input.parse::<Token![*]>()?;
+            Ok(FieldValue::Any(Ident::new("star", Span::call_site())))
+        } else if input.peek(Ident) {
+            let ident: Ident = input.parse()?;
+            if ident == "ANY" {
+                Ok(FieldValue::Any(ident))
+            } else {
+                Err(syn::Error::new(
+                    ident.span(),
+                    "expected ANY, *, a string literal, or an array",
+                ))
+            }
+        } else if input.peek(syn::token::Bracket) {
+            let content;
+            syn::bracketed!(content in input);
+            let mut values = Vec::new();
+            while !content.is_empty() {
+                values.push(content.parse()?);
+                if !content.is_empty() {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+            Ok(FieldValue::Array(values))
+        } else {
+            Ok(FieldValue::Literal(input.parse()?))
+        }
+    }
+}
+
+impl Parse for ReasonCode {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(LitInt) {
+            Ok(ReasonCode::Literal(input.parse()?))
+        } else {
+            Ok(ReasonCode::Ident(input.parse()?))
+        }
+    }
+}
+
+impl Parse for ConditionExpr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        syn::braced!(content in input);
+        let expr = Box::new(content.parse()?);
+        Ok(ConditionExpr { expr })
+    }
+}
+
+impl Parse for Condition {
+    fn parse(input: ParseStream) -> Result<Self> {
+        parse_condition_inner(input)
+    }
+}
+
+fn parse_condition_inner(input: ParseStream) -> Result<Condition> {
+    if input.peek(Ident) {
+        let lookahead = input.fork();
+        if let Ok(ident) = lookahead.parse::<Ident>()
+            && ident == "NOT"
+        {
+            let _: Ident = input.parse()?;
+            let condition = Condition::Not(Box::new(parse_atom(input)?));
+
+            return parse_rhs_condition(input, condition);
+        }
+    }
+
+    if let Some(attr) = parse_attr(input)
+        && input.peek(Ident)
+    {
+        let lookahead = input.fork();
+        if let Ok(ident) = lookahead.parse::<Ident>()
+            && let Some(lhs) = match ident.to_string().as_str() {
+                "EQ" => {
+                    let _: Ident = input.parse()?;
+                    let value = parse_operand(input)?;
+                    Some(Condition::Equals { attr, value })
+                }
+                "NEQ" => {
+                    let _: Ident = input.parse()?;
+                    let value = parse_operand(input)?;
+                    Some(Condition::NotEquals { attr, value })
+                }
+                "AT_LEAST" => {
+                    let _: Ident = input.parse()?;
+                    let role: LitStr = input.parse()?;
+                    Some(Condition::AtLeast {
+                        attr,
+                        role,
+                        roles: Vec::new(),
+                    })
+                }
+                _ => None,
+            }
+        {
+            return parse_rhs_condition(input, lhs);
+        }
+    }
+
+    let left = parse_atom(input)?;
+
+    if input.peek(Ident) {
+        let lookahead = input.fork();
+        if let Ok(ident) = lookahead.parse::<Ident>() {
+            let condition = match ident.to_string().as_str() {
+                "AND" => {
+                    let _: Ident = input.parse()?;
+                    let right = Box::new(parse_condition_inner(input)?);
+                    Condition::And(Box::new(left), right)
+                }
+                "OR" => {
+                    let _: Ident = input.parse()?;
+                    let right = Box::new(parse_condition_inner(input)?);
+                    Condition::Or(Box::new(left), right)
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        input.span(),
+                        "expected one of: EQ, NEQ, AND, OR",
+                    ));
+                }
+            };
+            if let Ok(ident) = input.parse::<Ident>() {
+                match ident.to_string().as_str() {
+                    "AND" => {
+                        let right = Box::new(parse_condition_inner(input)?);
+                        return Ok(Condition::And(Box::new(condition), right));
+                    }
+                    "OR" => {
+                        let right = Box::new(parse_condition_inner(input)?);
+                        return Ok(Condition::Or(Box::new(condition), right));
+                    }
+                    _ => {
+                        return Err(syn::Error::new(input.span(), "expected one of: AND, OR"));
+                    }
+                }
+            }
+            return Ok(condition);
+        }
+    }
+
+    Ok(left)
+}
+
+fn parse_rhs_condition(input: ParseStream, lhs: Condition) -> Result<Condition> {
+    if let Ok(ident) = input.parse::<Ident>() {
+        match ident.to_string().as_str() {
+            "AND" => {
+                let right = Box::new(parse_condition_inner(input)?);
+                Ok(Condition::And(Box::new(lhs), right))
+            }
+            "OR" => {
+                let right = Box::new(parse_condition_inner(input)?);
+                Ok(Condition::Or(Box::new(lhs), right))
+            }
+            _ => Err(syn::Error::new(input.span(), "expected one of: AND, OR")),
+        }
+    } else {
+        Ok(lhs)
+    }
+}
+
+/// The right-hand side of `EQ` and `NEQ`: a value, or a `$name` parameter.
+fn parse_operand(input: ParseStream) -> Result<Value> {
+    if input.peek(Token![$]) {
+        input.parse::<Token![$]>()?;
+        return Ok(Value::Param(input.parse()?));
+    }
+    input.parse()
+}
+
+impl Parse for Value {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(LitInt) {
+            return Ok(Value::Int(input.parse()?));
+        }
+        if input.peek(LitStr) {
+            return Ok(Value::Str(input.parse()?));
+        }
+        if input.peek(LitBool) {
+            return Ok(Value::Bool(input.parse()?));
+        }
+        Err(syn::Error::new(
+            input.span(),
+            format!("expected one of: {VALID_INT_TYPES_TEXT}"),
+        ))
+    }
+}
+
+fn parse_atom(input: ParseStream) -> Result<Condition> {
+    if input.peek(Paren) {
+        let content;
+        syn::parenthesized!(content in input);
+        return parse_condition_inner(&content);
+    }
+
+    if input.peek(LitBool) {
+        let lit_bool: LitBool = input.parse()?;
+        return if lit_bool.value {
+            Ok(Condition::True)
+        } else {
+            Ok(Condition::False)
+        };
+    }
+
+    Err(syn::Error::new(
+        input.span(),
+        "expected true, false, or a parenthesized condition",
+    ))
+}
+
+fn parse_attr(input: ParseStream) -> Option<String> {
+    if input.peek(LitStr) {
+        let lit_str: LitStr = input.parse().ok()?;
+        return Some(lit_str.value());
+    }
+    if input.peek(Ident) {
+        let lit_str: Ident = input.parse().ok()?;
+        return Some(lit_str.to_string());
+    }
+    None
+}
+
+/// The 1-based line and column of `span` in `source`, which it was parsed from.
+///
+/// Errors at the end of the input point at the empty call site span, so they are
+/// placed after the last character instead.
+pub(crate) fn location(span: Span, source: &str) -> (usize, usize) {
+    if span.byte_range().is_empty() {
+        let line = source.lines().count().max(1);
+        let column = source.lines().last().map_or(0, |line| line.chars().count()) + 1;
+        return (line, column);
+    }
+    let start = span.start();
+    (start.line, start.column + 1)
+}
@@ -0,0 +1,260 @@
+//! Loads policies written in the `policy_builder!` grammar at runtime.
+//!
+//! gate0 keeps the strings of targets and conditions as `&'static str`, so every
+//! string in a loaded policy is leaked. Policies are meant to be loaded once, at
+//! startup or on an explicit reload, not per request.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::ast::*;
+use crate::parse::location;
+use crate::validate::validate;
+
+/// An error in a policy source, located by 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The file the source was read from, if it came from a file.
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(span: proc_macro2::Span, message: impl Into<String>) -> Self {
+        let start = span.start();
+        ParseError {
+            path: None,
+            line: start.line,
+            column: start.column + 1,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<syn::Error> for ParseError {
+    fn from(err: syn::Error) -> Self {
+        ParseError::new(err.span(), err.to_string())
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ref path) = self.path {
+            write!(f, "{}:", path.display())?;
+        }
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses `source` and adds its rules to a new `gate0::PolicyBuilder`, resolving
+/// reason code identifiers through `reasons`.
+///
+/// The grammar is the one of `policy_builder!`, except that `USE`, `PARAMS` and `#[cfg(...)]`
+/// aren't available and `CONFIG` values have to be integer literals. `EXPECT` lines are parsed but
+/// not checked. The same limit checks the macro runs at compile time are reported
+/// as errors here.
+pub fn parse_policy(
+    source: &str,
+    reasons: &HashMap<&str, gate0::ReasonCode>,
+) -> Result<gate0::PolicyBuilder, ParseError> {
+    let policy: PolicyDefinition = syn::parse_str(source).map_err(|err| {
+        let (line, column) = location(err.span(), source);
+        ParseError {
+            path: None,
+            line,
+            column,
+            message: err.to_string(),
+        }
+    })?;
+
+    if let Some(use_builder) = policy.use_builder {
+        return Err(ParseError::new(
+            syn::spanned::Spanned::span(&use_builder),
+            "USE isn't supported at runtime",
+        ));
+    }
+    if let Some(param) = policy.params.first() {
+        return Err(ParseError::new(
+            param.name.span(),
+            "PARAMS isn't supported at runtime",
+        ));
+    }
+    if let Some(rule) = policy.rules.iter().find(|rule| !rule.cfg.is_empty()) {
+        return Err(ParseError::new(
+            rule.span,
+            "#[cfg] isn't supported at runtime",
+        ));
+    }
+    if let Some(diagnostic) = validate(&policy).into_iter().find(|d| d.is_error()) {
+        return Err(ParseError::new(diagnostic.span, diagnostic.message));
+    }
+
+    let mut builder = gate0::Policy::builder();
+    if let Some(ref config) = policy.config {
+        builder = builder.config(build_config(config)?);
+    }
+    for rule in &policy.rules {
+        builder = builder.rule(build_rule(rule, reasons)?);
+    }
+
+    Ok(builder)
+}
+
+/// Reads the file at `path` and parses it with [`parse_policy`]. Errors carry the path.
+pub fn parse_policy_file(
+    path: impl AsRef<Path>,
+    reasons: &HashMap<&str, gate0::ReasonCode>,
+) -> Result<gate0::PolicyBuilder, ParseError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|err| ParseError {
+        path: Some(path.to_path_buf()),
+        line: 0,
+        column: 0,
+        message: err.to_string(),
+    })?;
+    parse_policy(&source, reasons).map_err(|err| ParseError {
+        path: Some(path.to_path_buf()),
+        ..err
+    })
+}
+
+/// Builds the `gate0::PolicyConfig` of a `CONFIG` block the way [`parse_policy`] does.
+pub fn build_config(config: &ConfigBlock) -> Result<gate0::PolicyConfig, ParseError> {
+    let mut policy_config = gate0::PolicyConfig::default();
+    for field in &config.fields {
+        let value = match field.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(ref lit),
+                ..
+            }) => lit.base10_parse()?,
+            ref value => {
+                return Err(ParseError::new(
+                    syn::spanned::Spanned::span(value),
+                    "expected an integer literal",
+                ));
+            }
+        };
+        match field.name.to_string().as_str() {
+            "max_rules" => policy_config.max_rules = value,
+            "max_condition_depth" => policy_config.max_condition_depth = value,
+            "max_context_attrs" => policy_config.max_context_attrs = value,
+            "max_matcher_options" => policy_config.max_matcher_options = value,
+            "max_string_len" => policy_config.max_string_len = value,
+            _ => {
+                return Err(ParseError::new(
+                    field.name.span(),
+                    format!("unknown config field `{}`", field.name),
+                ));
+            }
+        }
+    }
+    Ok(policy_config)
+}
+
+/// Builds a single rule the way [`parse_policy`] does, for tools that evaluate rules
+/// on their own.
+pub fn build_rule(
+    rule: &RuleDefinition,
+    reasons: &HashMap<&str, gate0::ReasonCode>,
+) -> Result<gate0::Rule, ParseError> {
+    let effect = match rule.effect {
+        Effect::Allow => gate0::Effect::Allow,
+        Effect::Deny => gate0::Effect::Deny,
+    };
+    let reason = match rule.reason_code {
+        ReasonCode::Literal(ref lit) => gate0::ReasonCode(lit.base10_parse()?),
+        ReasonCode::Ident(ref ident) => {
+            let name = ident.to_string();
+            *reasons.get(name.as_str()).ok_or_else(|| {
+                ParseError::new(ident.span(), format!("unknown reason code `{name}`"))
+            })?
+        }
+    };
+    let condition = match rule.condition {
+        Some(ref condition) => Some(build_condition(&condition.expr)?),
+        None => None,
+    };
+
+    Ok(gate0::Rule::new(
+        effect,
+        build_target(&rule.target),
+        condition,
+        reason,
+    ))
+}
+
+fn build_target(target: &TargetSpec) -> gate0::Target {
+    match target {
+        TargetSpec::Any(_) => gate0::Target::any(),
+        TargetSpec::Tuple(t) => gate0::Target {
+            principal: build_matcher(Some(&t.principal)),
+            action: build_matcher(Some(&t.action)),
+            resource: build_matcher(Some(&t.resource)),
+        },
+        TargetSpec::Struct(s) => gate0::Target {
+            principal: build_matcher(s.field("principal")),
+            action: build_matcher(s.field("action")),
+            resource: build_matcher(s.field("resource")),
+        },
+    }
+}
+
+fn build_matcher(value: Option<&FieldValue>) -> gate0::Matcher {
+    match value {
+        Some(FieldValue::Literal(lit)) => gate0::Matcher::Exact(leak(lit.value())),
+        Some(FieldValue::Array(arr)) => {
+            let values = arr.iter().map(|lit| leak(lit.value())).collect::<Vec<_>>();
+            gate0::Matcher::OneOf(Box::leak(values.into_boxed_slice()))
+        }
+        Some(FieldValue::Any(_)) | None => gate0::Matcher::Any,
+    }
+}
+
+fn build_condition(condition: &Condition) -> Result<gate0::Condition, ParseError> {
+    Ok(match condition {
+        Condition::Equals { attr, value } => gate0::Condition::Equals {
+            attr: leak(attr.clone()),
+            value: build_value(value)?,
+        },
+        Condition::NotEquals { attr, value } => {
+            gate0::Condition::Not(Box::new(gate0::Condition::Equals {
+                attr: leak(attr.clone()),
+                value: build_value(value)?,
+            }))
+        }
+        Condition::And(left, right) => gate0::Condition::And(
+            Box::new(build_condition(left)?),
+            Box::new(build_condition(right)?),
+        ),
+        Condition::Or(left, right) => gate0::Condition::Or(
+            Box::new(build_condition(left)?),
+            Box::new(build_condition(right)?),
+        ),
+        Condition::Not(inner) => gate0::Condition::Not(Box::new(build_condition(inner)?)),
+        Condition::AtLeast { attr, roles, .. } => build_condition(&Condition::any_of(attr, roles))?,
+        Condition::True => gate0::Condition::True,
+        Condition::False => gate0::Condition::False,
+    })
+}
+
+fn build_value(value: &Value) -> Result<gate0::Value<'static>, ParseError> {
+    Ok(match value {
+        Value::Int(lit) => gate0::Value::Int(lit.base10_parse()?),
+        Value::Str(lit) => gate0::Value::String(leak(lit.value())),
+        Value::Bool(lit) => gate0::Value::Bool(lit.value),
+        Value::Param(name) => {
+            return Err(ParseError::new(
+                name.span(),
+                "PARAMS isn't supported at runtime",
+            ));
+        }
+    })
+}
+
+fn leak(value: String) -> &'static str {
+    Box::leak(value.into_boxed_str())
+}
@@ -0,0 +1,80 @@
+//! Finds the DSL macro invocations in Rust source, for tools that read `.rs` files
+//! instead of running inside the compiler.
+
+use proc_macro2::{Group, Ident, TokenStream, TokenTree};
+
+/// A `name! { ... }` invocation, with any delimiter.
+pub struct Invocation {
+    pub name: Ident,
+    pub body: Group,
+    /// Whether the body declares `macro_rules!` metavariables, as in `$name:expr` or
+    /// `$(...)*`, so it isn't a policy but a template for one.
+    pub template: bool,
+}
+
+/// Finds the invocations of the macros in `names` within `tokens`, in source order.
+///
+/// The bodies of found invocations aren't searched further, and neither are
+/// `macro_rules!` definitions, whose invocations are templates. A `$name` that
+/// doesn't declare a metavariable is policy syntax, such as a `PARAMS` parameter or
+/// a `FOR .. IN` variable.
+pub fn find_invocations(tokens: TokenStream, names: &[&str]) -> Vec<Invocation> {
+    let mut invocations = Vec::new();
+    collect(tokens, names, &mut invocations);
+    invocations
+}
+
+fn collect(tokens: TokenStream, names: &[&str], invocations: &mut Vec<Invocation>) {
+    let trees = tokens.into_iter().collect::<Vec<_>>();
+    let mut index = 0;
+    while index < trees.len() {
+        if let [
+            TokenTree::Ident(name),
+            TokenTree::Punct(bang),
+            TokenTree::Group(body),
+            ..,
+        ] = &trees[index..]
+            && bang.as_char() == '!'
+            && names.iter().any(|candidate| name == candidate)
+        {
+            invocations.push(Invocation {
+                name: name.clone(),
+                body: body.clone(),
+                template: declares_metavariables(body.stream()),
+            });
+            index += 3;
+            continue;
+        }
+        if let [TokenTree::Ident(name), TokenTree::Punct(bang), ..] = &trees[index..]
+            && name == "macro_rules"
+            && bang.as_char() == '!'
+        {
+            // Skips the name and the body.
+            index += 4;
+            continue;
+        }
+        if let TokenTree::Group(group) = &trees[index] {
+            collect(group.stream(), names, invocations);
+        }
+        index += 1;
+    }
+}
+
+/// Finds a `$name:fragment` or a `$(...)` repetition.
+fn declares_metavariables(tokens: TokenStream) -> bool {
+    let trees = tokens.into_iter().collect::<Vec<_>>();
+    trees.iter().enumerate().any(|(index, tree)| match tree {
+        TokenTree::Punct(dollar) if dollar.as_char() == '$' => match &trees[index + 1..] {
+            [TokenTree::Group(_), ..] => true,
+            [
+                TokenTree::Ident(_),
+                TokenTree::Punct(colon),
+                TokenTree::Ident(_),
+                ..,
+            ] => colon.as_char() == ':',
+            _ => false,
+        },
+        TokenTree::Group(group) => declares_metavariables(group.stream()),
+        _ => false,
+    })
+}
@@ -0,0 +1,482 @@
+//! Static checks on a parsed policy, reported as diagnostics with spans.
+//!
+//! Errors are problems `gate0::PolicyBuilder::build` would reject at runtime,
+//! caught early where the limits are known at parse time. Warnings point at rules
+//! that are valid but most likely not what was meant.
+
+use proc_macro2::Span;
+use syn::Ident;
+
+use crate::ast::*;
+use crate::parse::location;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            span,
+            message: message.into(),
+        }
+    }
+
+    fn warning(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message: message.into(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn to_syn_error(&self) -> syn::Error {
+        syn::Error::new(self.span, &self.message)
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// A diagnostic located by 1-based line and column in a policy source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDiagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for SourceDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.message
+        )
+    }
+}
+
+/// The `gate0::PolicyConfig` limits a policy is checked against.
+///
+/// A limit is `None` when it can't be known at parse time: when the `CONFIG`
+/// block sets it to something other than an integer literal, or when a `USE`
+/// builder may have configured it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_rules: Option<usize>,
+    pub max_condition_depth: Option<usize>,
+    pub max_matcher_options: Option<usize>,
+    pub max_string_len: Option<usize>,
+}
+
+impl Default for Limits {
+    /// The limits of `gate0::PolicyConfig::default()`.
+    fn default() -> Self {
+        Limits {
+            max_rules: Some(1000),
+            max_condition_depth: Some(10),
+            max_matcher_options: Some(64),
+            max_string_len: Some(256),
+        }
+    }
+}
+
+impl Limits {
+    pub fn for_policy(policy: &PolicyDefinition) -> Self {
+        let mut limits = if policy.use_builder.is_some() {
+            Limits {
+                max_rules: None,
+                max_condition_depth: None,
+                max_matcher_options: None,
+                max_string_len: None,
+            }
+        } else {
+            Limits::default()
+        };
+
+        for field in policy.config.iter().flat_map(|config| &config.fields) {
+            let value = match field.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(ref lit),
+                    ..
+                }) => lit.base10_parse().ok(),
+                _ => None,
+            };
+            match field.name.to_string().as_str() {
+                "max_rules" => limits.max_rules = value,
+                "max_condition_depth" => limits.max_condition_depth = value,
+                "max_matcher_options" => limits.max_matcher_options = value,
+                "max_string_len" => limits.max_string_len = value,
+                _ => {}
+            }
+        }
+
+        limits
+    }
+}
+
+/// Checks `policy` against the limits from its `CONFIG` block and returns every
+/// diagnostic found, errors and warnings alike.
+pub fn validate(policy: &PolicyDefinition) -> Vec<Diagnostic> {
+    let limits = Limits::for_policy(policy);
+    let mut diagnostics = Vec::new();
+
+    // Rules under `#[cfg]` may be left out, so only the unconditional ones are sure to
+    // count. The expansion checks the rest once the predicates are known.
+    let unconditional = policy
+        .rules
+        .iter()
+        .filter(|rule| rule.cfg.is_empty())
+        .collect::<Vec<_>>();
+    if let Some(max_rules) = limits.max_rules
+        && let Some(rule) = unconditional.get(max_rules)
+    {
+        let count = if unconditional.len() == policy.rules.len() {
+            format!("{} rules", unconditional.len())
+        } else {
+            format!("{} rules outside of `#[cfg]`", unconditional.len())
+        };
+        diagnostics.push(Diagnostic::error(
+            rule.span,
+            format!("the policy has {count}, more than max_rules ({max_rules})"),
+        ));
+    }
+
+    for (index, rule) in policy.rules.iter().enumerate() {
+        validate_attributes(rule, &policy.rules[..index], &mut diagnostics);
+        validate_target(&rule.target, rule.span, &limits, &mut diagnostics);
+        if let Some(ref condition) = rule.condition {
+            let depth = condition.expr.depth();
+            if let Some(max_depth) = limits.max_condition_depth
+                && depth > max_depth
+            {
+                diagnostics.push(Diagnostic::error(
+                    rule.span,
+                    format!(
+                        "the condition has depth {depth}, more than max_condition_depth ({max_depth})"
+                    ),
+                ));
+            }
+            validate_condition(&condition.expr, &limits, &mut diagnostics);
+        }
+    }
+    validate_params(policy, &mut diagnostics);
+    if let Some(ref hierarchy) = policy.roles {
+        let mut seen = Vec::new();
+        for role in hierarchy.chains.iter().flatten() {
+            if !seen.contains(&role.value()) {
+                seen.push(role.value());
+                check_string_len(role, &limits, &mut diagnostics);
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// The kind of macro a policy is written in, which decides whether it accepts `EXPECT`
+/// lines. `PARAMS` is only accepted by `policy_fn!`, checked with [`validate_fn`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    /// `policy_builder!`, `include_policy!` and `.gate0` files, which hand out the
+    /// builder the `EXPECT` lines are checked on.
+    Builder,
+    /// `decision_matrix!`, `policy_doc!`, `policy_coverage!`, `policy_catalog!` and
+    /// `policy_explain!`, which don't.
+    Derived,
+}
+
+/// Validates `policy` like [`validate`], first reporting `PARAMS` and, for
+/// [`Usage::Derived`], `EXPECT` as errors.
+pub fn validate_usage(policy: &PolicyDefinition, usage: Usage) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if usage == Usage::Derived
+        && let Some(expectation) = policy.expectations.first()
+    {
+        diagnostics.push(Diagnostic::error(
+            expectation.request.principal.span(),
+            "EXPECT is only available in `policy_builder!`, `include_policy!` and `policy_fn!`",
+        ));
+    }
+    if let Some(param) = policy.params.first() {
+        diagnostics.push(Diagnostic::error(
+            param.name.span(),
+            "PARAMS is only available in `policy_fn!`",
+        ));
+    }
+    diagnostics.extend(validate(policy));
+    diagnostics
+}
+
+/// Validates the policy of a `policy_fn!` like [`validate`], first reporting `EXPECT`,
+/// `COVERAGE` and `EXPLAIN` as errors when given together with `PARAMS`.
+pub fn validate_fn(function: &PolicyFn) -> Vec<Diagnostic> {
+    let policy = &function.policy;
+    let mut diagnostics = Vec::new();
+    if !policy.params.is_empty() {
+        if let Some(expectation) = policy.expectations.first() {
+            diagnostics.push(Diagnostic::error(
+                expectation.request.principal.span(),
+                "EXPECT isn't supported with PARAMS, as its outcome depends on the values passed in",
+            ));
+        }
+        for companion in &function.companions {
+            if let CompanionKind::Coverage { .. } | CompanionKind::Explain = companion.kind {
+                diagnostics.push(Diagnostic::error(
+                    companion.keyword.span(),
+                    format!(
+                        "{} isn't supported with PARAMS, as it builds the policy without any values",
+                        companion.keyword
+                    ),
+                ));
+            }
+        }
+    }
+    diagnostics.extend(validate(policy));
+    diagnostics
+}
+
+/// Parses `source` as a `policy_builder!` body and validates it with
+/// [`validate_usage`], reporting parse errors as error diagnostics.
+///
+/// Spans only carry locations outside of a proc macro.
+pub fn validate_source(source: &str) -> Vec<SourceDiagnostic> {
+    locate(
+        source,
+        syn::parse_str::<PolicyDefinition>(source)
+            .map(|policy| validate_usage(&policy, Usage::Builder)),
+    )
+}
+
+/// Like [`validate_source`], for the body of a `policy_fn!` validated with
+/// [`validate_fn`].
+pub fn validate_fn_source(source: &str) -> Vec<SourceDiagnostic> {
+    locate(
+        source,
+        syn::parse_str::<PolicyFn>(source).map(|function| validate_fn(&function)),
+    )
+}
+
+fn locate(source: &str, result: syn::Result<Vec<Diagnostic>>) -> Vec<SourceDiagnostic> {
+    let located = |span, severity, message| {
+        let (line, column) = location(span, source);
+        SourceDiagnostic {
+            severity,
+            line,
+            column,
+            message,
+        }
+    };
+
+    match result {
+        Ok(diagnostics) => diagnostics
+            .into_iter()
+            .map(|diagnostic| located(diagnostic.span, diagnostic.severity, diagnostic.message))
+            .collect(),
+        Err(err) => err
+            .into_iter()
+            .map(|err| located(err.span(), Severity::Error, err.to_string()))
+            .collect(),
+    }
+}
+
+/// Every `$name` refers to a parameter declared once in `PARAMS`, and every parameter is
+/// referred to.
+fn validate_params(policy: &PolicyDefinition, diagnostics: &mut Vec<Diagnostic>) {
+    fn references<'a>(condition: &'a Condition, found: &mut Vec<&'a Ident>) {
+        match condition {
+            Condition::Equals { value, .. } | Condition::NotEquals { value, .. } => {
+                if let Value::Param(name) = value {
+                    found.push(name);
+                }
+            }
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                references(left, found);
+                references(right, found);
+            }
+            Condition::Not(inner) => references(inner, found),
+            Condition::AtLeast { .. } | Condition::True | Condition::False => {}
+        }
+    }
+
+    let mut found = Vec::new();
+    for rule in &policy.rules {
+        if let Some(ref condition) = rule.condition {
+            references(&condition.expr, &mut found);
+        }
+    }
+
+    for name in &found {
+        if !policy.params.iter().any(|param| param.name == **name) {
+            diagnostics.push(Diagnostic::error(
+                name.span(),
+                format!("`${name}` isn't declared in PARAMS"),
+            ));
+        }
+    }
+    for (index, param) in policy.params.iter().enumerate() {
+        let name = &param.name;
+        if policy.params[..index]
+            .iter()
+            .any(|earlier| earlier.name == *name)
+        {
+            diagnostics.push(Diagnostic::error(
+                name.span(),
+                format!("the parameter `{name}` is declared more than once"),
+            ));
+        } else if !found.contains(&name) {
+            diagnostics.push(Diagnostic::error(
+                name.span(),
+                format!("the parameter `{name}` is never used"),
+            ));
+        }
+    }
+}
+
+/// Metadata attributes are given at most once per rule, and no two rules share an `id`.
+fn validate_attributes(
+    rule: &RuleDefinition,
+    earlier_rules: &[RuleDefinition],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (index, attribute) in rule.attributes.iter().enumerate() {
+        if rule.attributes[..index]
+            .iter()
+            .any(|earlier| earlier.name == attribute.name)
+        {
+            diagnostics.push(Diagnostic::error(
+                attribute.name.span(),
+                format!("`{}` is given more than once", attribute.name),
+            ));
+        }
+    }
+
+    if let Some(id) = rule.attribute("id")
+        && let Some(earlier) = earlier_rules
+            .iter()
+            .find(|earlier| earlier.attribute("id").map(syn::LitStr::value) == Some(id.value()))
+    {
+        diagnostics.push(Diagnostic::error(
+            id.span(),
+            format!(
+                "the id {:?} is already used by the rule on line {}",
+                id.value(),
+                earlier.line()
+            ),
+        ));
+    }
+}
+
+fn validate_target(
+    target: &TargetSpec,
+    span: Span,
+    limits: &Limits,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let values = match target {
+        TargetSpec::Any(_) => Vec::new(),
+        TargetSpec::Tuple(t) => vec![&t.principal, &t.action, &t.resource],
+        TargetSpec::Struct(s) => {
+            for (index, field) in s.fields.iter().enumerate() {
+                if s.fields[..index]
+                    .iter()
+                    .any(|earlier| earlier.name == field.name)
+                {
+                    diagnostics.push(Diagnostic::warning(
+                        field.name.span(),
+                        format!(
+                            "`{}` is given more than once; only the last value is used",
+                            field.name
+                        ),
+                    ));
+                }
+            }
+            s.fields.iter().map(|field| &field.value).collect()
+        }
+    };
+
+    for value in values {
+        match value {
+            FieldValue::Literal(lit) => check_string_len(lit, limits, diagnostics),
+            FieldValue::Array(arr) => {
+                if arr.is_empty() {
+                    diagnostics.push(Diagnostic::warning(
+                        span,
+                        "an empty array never matches, so the rule never applies",
+                    ));
+                }
+                if let Some(max_options) = limits.max_matcher_options
+                    && arr.len() > max_options
+                {
+                    diagnostics.push(Diagnostic::error(
+                        arr[max_options].span(),
+                        format!(
+                            "the array has {} options, more than max_matcher_options ({max_options})",
+                            arr.len()
+                        ),
+                    ));
+                }
+                for lit in arr {
+                    check_string_len(lit, limits, diagnostics);
+                }
+            }
+            FieldValue::Any(_) => {}
+        }
+    }
+}
+
+fn validate_condition(condition: &Condition, limits: &Limits, diagnostics: &mut Vec<Diagnostic>) {
+    match condition {
+        Condition::Equals { value, .. } | Condition::NotEquals { value, .. } => {
+            if let Value::Str(lit) = value {
+                check_string_len(lit, limits, diagnostics);
+            }
+        }
+        Condition::And(left, right) | Condition::Or(left, right) => {
+            validate_condition(left, limits, diagnostics);
+            validate_condition(right, limits, diagnostics);
+        }
+        Condition::Not(inner) => validate_condition(inner, limits, diagnostics),
+        // The roles are checked once, where `ROLES` declares them.
+        Condition::AtLeast { .. } | Condition::True | Condition::False => {}
+    }
+}
+
+fn check_string_len(lit: &syn::LitStr, limits: &Limits, diagnostics: &mut Vec<Diagnostic>) {
+    let len = lit.value().len();
+    if let Some(max_len) = limits.max_string_len
+        && len > max_len
+    {
+        diagnostics.push(Diagnostic::error(
+            lit.span(),
+            format!("the string is {len} bytes long, more than max_string_len ({max_len})"),
+        ));
+    }
+}
@@ -0,0 +1,864 @@
+use gate0_dsl_core::{
+    ast::{Condition, IncludePolicy, PolicyDefinition},
+    validate::{Limits, Severity, validate},
+};
+
+fn parse(source: &str) -> PolicyDefinition {
+    syn::parse_str(source).unwrap()
+}
+
+fn messages(source: &str) -> Vec<(Severity, String)> {
+    validate(&parse(source))
+        .into_iter()
+        .map(|diagnostic| (diagnostic.severity, diagnostic.message))
+        .collect()
+}
+
+#[test]
+fn test_rules_render_as_dsl() {
+    let policy = parse(
+        r#"
+        ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => MEMBER_READ;
+        DENY ("eve" * ANY) WHERE { NOT (verified EQ true) OR "user-agent" NEQ "bot" } => 3;
+
+        EXPECT ("bob" "read" "doc1") WITH { role => "member" } => ALLOW;
+        "#,
+    );
+
+    let rules = policy
+        .rules
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => MEMBER_READ;"#,
+            r#"DENY ("eve" * ANY) WHERE { NOT (verified EQ true) OR "user-agent" NEQ "bot" } => 3;"#,
+        ]
+    );
+    assert_eq!(policy.expectations.len(), 1);
+    assert_eq!(policy.rules[0].line(), 2);
+    assert_eq!(policy.rules[1].line(), 3);
+}
+
+#[test]
+fn test_parse_error_has_location() {
+    let Err(err) = syn::parse_str::<PolicyDefinition>("ALLOW ANY => 1;\nALLOW NONE => 2;") else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(err.to_string(), "expected ANY, *, tuple, or struct");
+    assert_eq!(err.span().start().line, 2);
+    assert_eq!(err.span().start().column, 6);
+}
+
+#[test]
+fn test_for_blocks() {
+    let policy = parse(
+        r#"
+        FOR { action: ["read", "list"] } {
+            ALLOW WHERE { role EQ "member" } => 1;
+            FOR ("bob" * *) {
+                DENY { action: "list" } => 2;
+            }
+        }
+        FOR ANY { DENY => 3; }
+        "#,
+    );
+    let rules = policy
+        .rules
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => 1;"#,
+            r#"DENY { principal: "bob", action: "list" } => 2;"#,
+            "DENY ANY => 3;",
+        ]
+    );
+    assert_eq!(policy.rules[1].line(), 5);
+    // The fields point at the targets their values come from.
+    let target = &policy.rules[1].target;
+    assert_eq!(target.field_span("principal").start().line, 4);
+    assert_eq!(target.field_span("action").start().line, 5);
+    assert_eq!(policy.rules[2].target.span().start().line, 8);
+
+    let Err(err) = syn::parse_str::<PolicyDefinition>(
+        "FOR { action: [\"read\", \"list\"] } {\n    ALLOW { action: [\"read\", \"write\"] } => 1;\n}",
+    ) else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(
+        err.to_string(),
+        r#""write" is outside of the FOR block's action ["read", "list"]"#
+    );
+    assert_eq!(err.span().start().line, 2);
+    assert_eq!(err.span().start().column, 29);
+
+    let Err(err) = syn::parse_str::<PolicyDefinition>("ALLOW => 1;") else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(err.to_string(), "expected ANY, *, tuple, or struct");
+}
+
+#[test]
+fn test_with_blocks() {
+    let source = r#"
+        CONFIG { max_condition_depth: 3 };
+        WITH { tenant_tier EQ "enterprise" } {
+            ALLOW ANY => 1;
+            WITH { region EQ "eu" OR region EQ "us" } {
+                ALLOW { action: "read" } WHERE { role EQ "member" } => 2;
+            }
+        }
+        "#;
+    let policy = parse(source);
+    let rules = policy
+        .rules
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"ALLOW ANY WHERE { tenant_tier EQ "enterprise" } => 1;"#,
+            r#"ALLOW { action: "read" } WHERE { (tenant_tier EQ "enterprise" AND (region EQ "eu" OR region EQ "us")) AND role EQ "member" } => 2;"#,
+        ]
+    );
+    assert_eq!(
+        messages(source),
+        [(
+            Severity::Error,
+            String::from("the condition has depth 4, more than max_condition_depth (3)")
+        )]
+    );
+}
+
+#[test]
+fn test_validate_params() {
+    let policy = parse(
+        r#"
+        PARAMS(tenant: &'static str, min_level: i64);
+        ALLOW ANY WHERE { tenant EQ $tenant AND level NEQ $min_level } => 1;
+        "#,
+    );
+    assert_eq!(policy.params.len(), 2);
+    assert_eq!(
+        policy.rules[0].to_string(),
+        "ALLOW ANY WHERE { tenant EQ $tenant AND level NEQ $min_level } => 1;"
+    );
+
+    assert_eq!(
+        messages(
+            r#"
+            PARAMS(tenant: &'static str, tenant: &'static str, unused: bool);
+            ALLOW ANY WHERE { tenant EQ $tenant OR region EQ $region } => 1;
+            "#
+        ),
+        [
+            (
+                Severity::Error,
+                String::from("`$region` isn't declared in PARAMS")
+            ),
+            (
+                Severity::Error,
+                String::from("the parameter `tenant` is declared more than once")
+            ),
+            (
+                Severity::Error,
+                String::from("the parameter `unused` is never used")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_for_loops() {
+    let policy = parse(
+        r#"
+        FOR tier IN ["gold", "silver"] {
+            FOR region IN ["eu", "us"] {
+                #[id = "$tier-$region"]
+                ALLOW { principal: "svc-$region", action: "$tiers" }
+                    WHERE { region EQ $region AND tier EQ $tier } => 1;
+            }
+        }
+        "#,
+    );
+    let rules = policy
+        .rules
+        .iter()
+        .map(|rule| format!("{} {}", rule.attribute("id").unwrap().value(), rule))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"gold-eu ALLOW { principal: "svc-eu", action: "$tiers" } WHERE { region EQ "eu" AND tier EQ "gold" } => 1;"#,
+            r#"gold-us ALLOW { principal: "svc-us", action: "$tiers" } WHERE { region EQ "us" AND tier EQ "gold" } => 1;"#,
+            r#"silver-eu ALLOW { principal: "svc-eu", action: "$tiers" } WHERE { region EQ "eu" AND tier EQ "silver" } => 1;"#,
+            r#"silver-us ALLOW { principal: "svc-us", action: "$tiers" } WHERE { region EQ "us" AND tier EQ "silver" } => 1;"#,
+        ]
+    );
+    assert!(policy.rules.iter().all(|rule| rule.line() == 5));
+
+    let Err(err) = syn::parse_str::<PolicyDefinition>(
+        "FOR { action: \"read\" } {\n    FOR a IN [\"read\", \"write\"] {\n        ALLOW { action: \"$a\" } => 1;\n    }\n}",
+    ) else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(
+        err.to_string(),
+        r#""write" is outside of the FOR block's action "read""#
+    );
+    assert_eq!(err.span().start().line, 3);
+    assert_eq!(err.span().start().column, 24);
+
+    let Err(err) = syn::parse_str::<PolicyDefinition>(
+        r#"FOR a IN ["x"] { FOR a IN ["y"] { ALLOW ANY => 1; } }"#,
+    ) else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(
+        err.to_string(),
+        "`a` is already the variable of an enclosing loop"
+    );
+}
+
+#[test]
+fn test_role_hierarchy() {
+    let policy = parse(
+        r#"
+        ROLES { owner > admin > member > viewer, owner > "billing-admin" > member };
+        ALLOW ANY WHERE { role AT_LEAST "member" } => 1;
+        ALLOW ANY WHERE { NOT (role AT_LEAST "admin") } => 2;
+        "#,
+    );
+    let rule = &policy.rules[0];
+    assert_eq!(
+        rule.to_string(),
+        r#"ALLOW ANY WHERE { role AT_LEAST "member" } => 1;"#
+    );
+    let Condition::AtLeast {
+        ref attr,
+        ref roles,
+        ..
+    } = *rule.condition.as_ref().unwrap().expr
+    else {
+        panic!("expected AT_LEAST");
+    };
+    assert_eq!(
+        Condition::any_of(attr, roles).to_string(),
+        r#"(role EQ "owner" OR role EQ "admin") OR (role EQ "member" OR role EQ "billing-admin")"#
+    );
+    assert_eq!(rule.condition.as_ref().unwrap().expr.depth(), 3);
+    assert_eq!(
+        policy.rules[1].condition.as_ref().unwrap().expr.describe(),
+        r#"not (role is at least "admin")"#
+    );
+
+    for (source, message) in [
+        (
+            "ROLES { owner > admin, admin > member > owner };",
+            r#""owner" would be above itself in ROLES"#,
+        ),
+        (
+            r#"ROLES { owner > admin }; ALLOW ANY WHERE { role AT_LEAST "guest" } => 1;"#,
+            r#""guest" isn't declared in ROLES"#,
+        ),
+        (
+            r#"ALLOW ANY WHERE { role AT_LEAST "admin" } => 1;"#,
+            "AT_LEAST needs the roles declared with ROLES { .. };",
+        ),
+    ] {
+        let Err(err) = syn::parse_str::<PolicyDefinition>(source) else {
+            panic!("expected a parse error for {source}");
+        };
+        assert_eq!(err.to_string(), message);
+    }
+}
+
+#[test]
+fn test_header_order() {
+    let policy = parse(
+        r#"
+        COMBINE FIRST_MATCH;
+        ROLES { admin > member }
+        CONFIG { max_rules: 10 };
+        USE builder;
+        ALLOW ANY WHERE { role AT_LEAST "member" } => 1;
+        "#,
+    );
+    assert!(policy.first_match);
+    assert!(policy.roles.is_some());
+    assert!(policy.config.is_some());
+    assert!(policy.use_builder.is_some());
+    assert_eq!(policy.rules.len(), 1);
+
+    for (source, message) in [
+        (
+            "ROLES { admin > member }; ROLES { owner > admin };",
+            "ROLES is already given above",
+        ),
+        (
+            "ALLOW ANY => 1; CONFIG { max_rules: 10 };",
+            "CONFIG has to come before the rules",
+        ),
+    ] {
+        let Err(err) = syn::parse_str::<PolicyDefinition>(source) else {
+            panic!("expected a parse error for {source}");
+        };
+        assert_eq!(err.to_string(), message);
+    }
+}
+
+#[test]
+fn test_rbac_tables() {
+    let policy = parse(
+        r#"
+        RBAC {
+            attr: role;
+            reason: RBAC_ALLOW;
+            admin => *;
+            member => ["read", "list"];
+            "read-only" => "read" => AUDIT_READ;
+        }
+        "#,
+    );
+    let rules = policy
+        .rules
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"ALLOW { action: * } WHERE { role EQ "admin" } => RBAC_ALLOW;"#,
+            r#"ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => RBAC_ALLOW;"#,
+            r#"ALLOW { action: "read" } WHERE { role EQ "read-only" } => AUDIT_READ;"#,
+        ]
+    );
+    assert_eq!(policy.rules[2].line(), 7);
+
+    // Rows without a reason code of their own or the table's get `0`.
+    let policy = parse(
+        r#"RBAC { attr: role; admin => *; member => ["read", "list"]; auditor => ["read"] => AUDIT_READ; }"#,
+    );
+    assert_eq!(
+        policy
+            .rules
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        [
+            r#"ALLOW { action: * } WHERE { role EQ "admin" } => 0;"#,
+            r#"ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => 0;"#,
+            r#"ALLOW { action: ["read"] } WHERE { role EQ "auditor" } => AUDIT_READ;"#,
+        ]
+    );
+
+    for (source, message) in [
+        (
+            "RBAC { attr: role; admin => * => 1; admin => \"read\" => 2; }",
+            r#"the role "admin" is listed more than once"#,
+        ),
+        (
+            "RBAC { reason: 1; admin => *; }",
+            "expected `attr: <attribute>;` to start the table",
+        ),
+    ] {
+        let Err(err) = syn::parse_str::<PolicyDefinition>(source) else {
+            panic!("expected a parse error for {source}");
+        };
+        assert_eq!(err.to_string(), message);
+    }
+}
+
+#[test]
+fn test_decision_tables() {
+    let policy = parse(
+        r#"
+        TABLE COMPLETE (role: ["admin", "member"], region: ["eu", "us"], action, level) {
+            ("admin", *, *, *) => ALLOW 1;
+            ("member", "eu", ["read", "list"], *) => ALLOW 2;
+            ("member", "us", "read", [2, 3]) => ALLOW 3;
+            ("member", "us", *, *) => DENY 4;
+        }
+        "#,
+    );
+    let rules = policy
+        .rules
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"ALLOW ANY WHERE { role EQ "admin" } => 1;"#,
+            r#"ALLOW { action: ["read", "list"] } WHERE { role EQ "member" AND region EQ "eu" } => 2;"#,
+            r#"ALLOW { action: "read" } WHERE { (role EQ "member" AND region EQ "us") AND (level EQ 2 OR level EQ 3) } => 3;"#,
+            r#"DENY ANY WHERE { role EQ "member" AND region EQ "us" } => 4;"#,
+        ]
+    );
+    assert_eq!(policy.rules[3].line(), 6);
+
+    // Without COMPLETE, declared values only restrict the cells.
+    let policy = parse(
+        r#"
+        TABLE (role: ["admin", "member"], mfa: [true, false]) {
+            ("admin", *) => ALLOW 1;
+        }
+        "#,
+    );
+    assert_eq!(policy.rules.len(), 1);
+
+    // Cells are compared with the declared values by what they denote.
+    let policy = parse(
+        r#"
+        TABLE COMPLETE (level: [1, 2], team: ["ops"]) {
+            (0x1, r"ops") => ALLOW 1;
+            (0b10, *) => ALLOW 2;
+        }
+        "#,
+    );
+    assert_eq!(policy.rules.len(), 2);
+
+    let values = (0..65)
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let source =
+        format!(r#"TABLE COMPLETE (a: [{values}], b: [{values}]) {{ (*, *) => ALLOW 1; }}"#);
+    let Err(err) = syn::parse_str::<PolicyDefinition>(&source) else {
+        panic!("expected a parse error for {source}");
+    };
+    assert_eq!(
+        err.to_string(),
+        "TABLE COMPLETE checks at most 4096 combinations of the declared values"
+    );
+
+    for (source, message) in [
+        (
+            r#"TABLE (role, action) { ("admin", *, *) => ALLOW 1; }"#,
+            "the row has 3 cells, but the table has 2 columns",
+        ),
+        (
+            r#"TABLE (role, role) { (*, *) => ALLOW 1; }"#,
+            r#"the column "role" is listed more than once"#,
+        ),
+        (
+            r#"TABLE (role, action) { ("admin", 3) => ALLOW 1; }"#,
+            r#"the "action" column only takes strings"#,
+        ),
+        (
+            r#"TABLE (role: ["admin"]) { ("member") => ALLOW 1; }"#,
+            r#""member" isn't one of the declared values of "role""#,
+        ),
+        (
+            r#"TABLE COMPLETE (role: ["admin", "member"], mfa: [true, false], action) {
+                ("admin", *, *) => ALLOW 1;
+                ("member", true, "read") => ALLOW 2;
+            }"#,
+            r#"no row covers role EQ "member" AND mfa EQ false"#,
+        ),
+        (
+            r#"TABLE COMPLETE (role, action) { ("admin", *) => ALLOW 1; }"#,
+            "TABLE COMPLETE needs a column with declared values",
+        ),
+        (
+            r#"TABLE FULL (role: ["admin"]) { ("admin") => ALLOW 1; }"#,
+            "expected COMPLETE",
+        ),
+    ] {
+        let Err(err) = syn::parse_str::<PolicyDefinition>(source) else {
+            panic!("expected a parse error for {source}");
+        };
+        assert_eq!(err.to_string(), message);
+    }
+}
+
+#[test]
+fn test_else_chains() {
+    let policy = parse(
+        r#"
+        #[id = "export"]
+        ALLOW { action: "export" } WHERE { plan EQ "premium" } => EXPORT_OK
+            ELSE IF { plan EQ "trial" } DENY => EXPORT_TRIAL
+            ELSE IF { role EQ "admin" } ALLOW => EXPORT_ADMIN
+            ELSE DENY => EXPORT_NEEDS_PREMIUM;
+        "#,
+    );
+    let rules = policy
+        .rules
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"ALLOW { action: "export" } WHERE { plan EQ "premium" } => EXPORT_OK;"#,
+            r#"DENY { action: "export" } WHERE { NOT (plan EQ "premium") AND plan EQ "trial" } => EXPORT_TRIAL;"#,
+            r#"ALLOW { action: "export" } WHERE { (NOT (plan EQ "premium") AND NOT (plan EQ "trial")) AND role EQ "admin" } => EXPORT_ADMIN;"#,
+            r#"DENY { action: "export" } WHERE { (NOT (plan EQ "premium") AND NOT (plan EQ "trial")) AND NOT (role EQ "admin") } => EXPORT_NEEDS_PREMIUM;"#,
+        ]
+    );
+    assert_eq!(policy.rules[2].line(), 5);
+    assert!(policy.rules[1].attribute("id").is_none());
+
+    for (source, message) in [
+        (
+            "ALLOW ANY => 1 ELSE DENY => 2;",
+            "ELSE can only follow a branch with a condition",
+        ),
+        (
+            "ALLOW ANY WHERE { a EQ 1 } => 1 ELSE DENY => 2 ELSE DENY => 3;",
+            "ELSE can only follow a branch with a condition",
+        ),
+        (
+            "ALLOW ANY WHERE { a EQ 1 } => 1 ELSE DENY WHERE { a EQ 2 } => 2;",
+            "a branch with a condition is written `ELSE IF { <condition> } <ALLOW|DENY>`",
+        ),
+        (
+            "ALLOW ANY WHERE { a EQ 1 } => 1 OTHERWISE DENY => 2;",
+            "expected `;` or ELSE",
+        ),
+    ] {
+        let Err(err) = syn::parse_str::<PolicyDefinition>(source) else {
+            panic!("expected a parse error for {source}");
+        };
+        assert_eq!(err.to_string(), message);
+    }
+
+    let depth = r#"
+        CONFIG { max_condition_depth: 2 };
+        ALLOW ANY WHERE { a EQ 1 } => 1
+            ELSE IF { a EQ 2 } ALLOW => 2
+            ELSE DENY => 3;
+    "#;
+    let error = (
+        Severity::Error,
+        String::from("the condition has depth 3, more than max_condition_depth (2)"),
+    );
+    assert_eq!(messages(depth), [error.clone(), error]);
+}
+
+#[test]
+fn test_match_blocks() {
+    let policy = parse(
+        r#"
+        MATCH role {
+            "admin" => ALLOW ANY => ADMIN;
+            "member" => ALLOW { action: ["read"] } WHERE { mfa EQ true } => MEMBER_READ;
+            _ => DENY ANY => NO_ROLE;
+        }
+        "#,
+    );
+    let rules = policy
+        .rules
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"ALLOW ANY WHERE { role EQ "admin" } => ADMIN;"#,
+            r#"ALLOW { action: ["read"] } WHERE { role EQ "member" AND mfa EQ true } => MEMBER_READ;"#,
+            r#"DENY ANY WHERE { role NEQ "admin" AND role NEQ "member" } => NO_ROLE;"#,
+        ]
+    );
+    assert_eq!(policy.rules[2].line(), 5);
+
+    // The guard of the `_` arm counts its NEQ comparisons as NOT (EQ).
+    let depth = r#"
+        CONFIG { max_condition_depth: 2 };
+        MATCH role {
+            "admin" => ALLOW ANY => 1;
+            _ => DENY ANY => 2;
+        }
+        MATCH plan {
+            "free" => DENY ANY => 3;
+            "pro" => ALLOW ANY => 4;
+            _ => DENY ANY => 5;
+        }
+    "#;
+    assert_eq!(
+        messages(depth),
+        [(
+            Severity::Error,
+            String::from("the condition has depth 3, more than max_condition_depth (2)")
+        )]
+    );
+
+    for (source, message) in [
+        (
+            r#"MATCH role { "admin" => ALLOW ANY => 1; "admin" => DENY ANY => 2; }"#,
+            r#"the arm "admin" is listed more than once"#,
+        ),
+        (
+            r#"MATCH role { _ => DENY ANY => 1; "admin" => ALLOW ANY => 2; }"#,
+            "the `_` arm has to be the last one",
+        ),
+    ] {
+        let Err(err) = syn::parse_str::<PolicyDefinition>(source) else {
+            panic!("expected a parse error for {source}");
+        };
+        assert_eq!(err.to_string(), message);
+    }
+}
+
+#[test]
+fn test_combine_first_match() {
+    let policy = parse(
+        r#"
+        COMBINE FIRST_MATCH;
+        DENY ("eve" * *) WHERE { suspended EQ true } => 1;
+        ALLOW (["eve", "bob"] ["read", "write"] *) WHERE { verified EQ true } => 2;
+        #[id = "no-writes"]
+        DENY (["eve", "bob", "alice"] "write" *) => 3;
+        ALLOW ANY => 4;
+        "#,
+    );
+    assert!(policy.first_match);
+    let rules = policy
+        .rules
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            r#"DENY ("eve" * *) WHERE { suspended EQ true } => 1;"#,
+            r#"ALLOW (["eve", "bob"] ["read", "write"] *) WHERE { verified EQ true } => 2;"#,
+            r#"DENY { principal: "alice", action: "write" } => 3;"#,
+            r#"DENY { principal: ["eve", "bob"], action: "write" } WHERE { NOT (verified EQ true) } => 3;"#,
+            r#"ALLOW ANY => 4;"#,
+        ]
+    );
+    assert!(policy.rules[2].attribute("id").is_some());
+    assert!(policy.rules[3].attribute("id").is_none());
+
+    // Specific rules followed by a catch-all are left as they are.
+    let catch_all = parse(
+        r#"
+        COMBINE FIRST_MATCH;
+        DENY ("eve" * *) => 1;
+        DENY (* "delete" *) WHERE { role EQ "guest" } => 2;
+        ALLOW ANY WHERE { role EQ "admin" } => 3;
+        DENY ANY => 4;
+        "#,
+    );
+    assert_eq!(
+        catch_all
+            .rules
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        [
+            r#"DENY ("eve" * *) => 1;"#,
+            r#"DENY (* "delete" *) WHERE { role EQ "guest" } => 2;"#,
+            r#"ALLOW ANY WHERE { role EQ "admin" } => 3;"#,
+            r#"DENY ANY WHERE { NOT (role EQ "admin") } => 4;"#,
+        ]
+    );
+
+    // A last DENY that can't be split only decides requests that gate0 denies anyway.
+    let trailing_deny = parse(
+        r#"
+        COMBINE FIRST_MATCH;
+        ALLOW ("alice" "read" *) => 1;
+        DENY ANY => 2;
+        "#,
+    );
+    assert_eq!(
+        trailing_deny
+            .rules
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        [r#"ALLOW ("alice" "read" *) => 1;"#]
+    );
+
+    for (source, message) in [
+        (
+            r#"COMBINE LAST_MATCH; ALLOW ANY => 1;"#,
+            "expected FIRST_MATCH",
+        ),
+        (
+            r#"COMBINE FIRST_MATCH; ALLOW ("bob" "read" *) => 1; DENY ("bob" "read" *) => 2;"#,
+            "this rule never applies with COMBINE FIRST_MATCH, the rules before it match all \
+             of its requests",
+        ),
+        (
+            r#"COMBINE FIRST_MATCH; DENY ("bob" * *) => 1; ALLOW ("bob" "read" *) => 2;"#,
+            "this rule never applies with COMBINE FIRST_MATCH, the rules before it match all \
+             of its requests",
+        ),
+        (
+            r#"COMBINE FIRST_MATCH; ALLOW ("eve" * *) => 1; DENY ANY WHERE { suspended EQ true } => 2; ALLOW ANY => 3;"#,
+            r#"COMBINE FIRST_MATCH can't leave the principal ["eve"] of an earlier ALLOW out of this DENY's `*`; list the principal values instead"#,
+        ),
+        (
+            r#"COMBINE FIRST_MATCH; #[cfg(test)] ALLOW ANY => 1;"#,
+            "#[cfg] isn't supported with COMBINE FIRST_MATCH",
+        ),
+    ] {
+        let Err(err) = syn::parse_str::<PolicyDefinition>(source) else {
+            panic!("expected a parse error for {source}");
+        };
+        assert_eq!(err.to_string(), message);
+    }
+}
+
+#[test]
+fn test_validate_limits() {
+    assert_eq!(
+        messages(
+            r#"
+            CONFIG { max_rules: 1, max_condition_depth: 2 };
+            ALLOW ANY WHERE { NOT (role EQ "admin" AND true) } => 1;
+            ALLOW ANY => 2;
+            "#
+        ),
+        [
+            (
+                Severity::Error,
+                String::from("the policy has 2 rules, more than max_rules (1)")
+            ),
+            (
+                Severity::Error,
+                String::from("the condition has depth 3, more than max_condition_depth (2)")
+            ),
+        ]
+    );
+
+    // NEQ is built as NOT (EQ), one level deeper than EQ.
+    assert_eq!(
+        messages(
+            r#"
+            CONFIG { max_condition_depth: 2 };
+            ALLOW ANY WHERE { a NEQ 1 AND b NEQ 2 } => 1;
+            ALLOW ANY WHERE { a NEQ 1 } => 2;
+            "#
+        ),
+        [(
+            Severity::Error,
+            String::from("the condition has depth 3, more than max_condition_depth (2)")
+        )]
+    );
+}
+
+#[test]
+fn test_validate_max_rules_with_cfg() {
+    let source = |max_rules| {
+        format!(
+            r#"
+            CONFIG {{ max_rules: {max_rules} }};
+            ALLOW ANY => 1;
+            #[cfg(test)]
+            {{
+                ALLOW ANY => 2;
+                #[cfg(unix)] ALLOW ANY => 3;
+            }}
+            DENY ANY => 4;
+            "#
+        )
+    };
+
+    let policy = parse(&source(2));
+    assert_eq!(policy.rules.len(), 4);
+    assert_eq!(policy.rules[2].cfg_text().unwrap(), "all(test, unix)");
+    assert!(messages(&source(2)).is_empty());
+    assert_eq!(
+        messages(&source(1)),
+        [(
+            Severity::Error,
+            String::from("the policy has 2 rules outside of `#[cfg]`, more than max_rules (1)")
+        )]
+    );
+}
+
+#[test]
+fn test_validate_warnings() {
+    assert_eq!(
+        messages(r#"ALLOW { action: [], action: "read" } => 1;"#),
+        [
+            (
+                Severity::Warning,
+                String::from("`action` is given more than once; only the last value is used")
+            ),
+            (
+                Severity::Warning,
+                String::from("an empty array never matches, so the rule never applies")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_rule_attributes() {
+    let policy = parse(
+        r#"
+        /// Members can read.
+        #[id = "member-read"]
+        #[owner = "team-docs"]
+        ALLOW { action: "read" } WHERE { role EQ "member" } => MEMBER_READ;
+        "#,
+    );
+    let rule = &policy.rules[0];
+    assert_eq!(rule.description(), "Members can read.");
+    assert_eq!(rule.attribute("owner").unwrap().value(), "team-docs");
+    assert_eq!(rule.line(), 5);
+
+    assert_eq!(
+        messages(
+            r#"
+            #[id = "read"] #[id = "list"] ALLOW ANY => 1;
+            #[id = "read"] DENY ANY => 2;
+            "#
+        ),
+        [
+            (
+                Severity::Error,
+                String::from("`id` is given more than once")
+            ),
+            (
+                Severity::Error,
+                String::from(r#"the id "read" is already used by the rule on line 2"#)
+            ),
+        ]
+    );
+
+    let Err(err) = syn::parse_str::<PolicyDefinition>("#[owner(team)] ALLOW ANY => 1;") else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(
+        err.to_string(),
+        r#"expected a doc comment, `#[cfg(...)]` or a `#[name = "value"]` attribute"#
+    );
+}
+
+#[test]
+fn test_limits_unknown_with_external_builder() {
+    let policy = parse("USE builder; CONFIG { max_rules: 5, max_string_len: LEN };");
+    assert_eq!(
+        Limits::for_policy(&policy),
+        Limits {
+            max_rules: Some(5),
+            max_condition_depth: None,
+            max_matcher_options: None,
+            max_string_len: None,
+        }
+    );
+}
+
+#[test]
+fn test_include_policy_errors() {
+    // Outside of a proc macro, the tokens parsed from the file carry their positions.
+    let include: IncludePolicy = syn::parse_str(r#""tests/fixtures/broken.gate0""#).unwrap();
+    let expanded = include.expand().to_string();
+    assert!(
+        expanded.contains("tests/fixtures/broken.gate0:2:7: expected ANY, *, tuple, or struct"),
+        "{expanded}"
+    );
+}
@@ -0,0 +1,1200 @@
+//! A formatter for the gate0 policy DSL.
+//!
+//! rustfmt leaves macro bodies alone, so this formats `.gate0` policy files and the bodies
+//! of `policy_builder!`, `decision_matrix!`, `policy_coverage!`, `policy_explain!`,
+//! `policy_doc!`, `policy_catalog!`, `policy_fn!` and `ctx!` invocations in Rust files
+//! into one canonical layout:
+//!
+//! - `=>` aligned across consecutive one-line rules, and across consecutive `EXPECT` lines,
+//!   up to the next blank line
+//! - struct targets in principal, action, resource order
+//! - `ANY` for whole targets and `*` for single fields
+//! - rules longer than [`MAX_WIDTH`] get their `WHERE` clause on its own line, split at
+//!   the top-level `AND`s or `OR`s if that's still too long
+//!
+//! Comments between statements are kept where they are. Comments inside a statement
+//! follow the token they follow as written, or go above the statement when that token
+//! is gone from the formatted statement or the comment spans lines.
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use gate0_dsl_core::ast::{
+    Condition, ConditionExpr, ConfigBlock, CtxDefinition, DecisionTable, Expectation, FieldValue,
+    MatchBlock, RbacTable, RuleChain, RuleDefinition, StructTarget, StructTargetField, TableCell,
+    TargetSpec, Value,
+};
+use gate0_dsl_core::source::{Invocation, find_invocations};
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{LitStr, Token};
+
+/// Lines longer than this are wrapped where the grammar allows it.
+pub const MAX_WIDTH: usize = 100;
+
+const INDENT: &str = "    ";
+const POLICY_MACROS: &[&str] = &[
+    "policy_builder",
+    "decision_matrix",
+    "policy_coverage",
+    "policy_explain",
+    "policy_doc",
+    "policy_catalog",
+    "policy_fn",
+];
+const CTX_MACRO: &str = "ctx";
+
+/// A syntax error in the formatted source, located by 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl FormatError {
+    fn new(span: proc_macro2::Span, message: impl Into<String>) -> Self {
+        let start = span.start();
+        FormatError {
+            line: start.line,
+            column: start.column + 1,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<syn::Error> for FormatError {
+    fn from(err: syn::Error) -> Self {
+        FormatError::new(err.span(), err.to_string())
+    }
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Formats the contents of a `.gate0` file.
+pub fn format_policy(source: &str) -> Result<String, FormatError> {
+    let trees = lex(source)?.into_iter().collect::<Vec<_>>();
+    if trees.is_empty() && source.trim().is_empty() {
+        return Ok(String::new());
+    }
+    let lines = format_statements(source, &trees, 0..source.len(), "")?;
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Formats the DSL macro invocations in the contents of a `.rs` file, leaving
+/// everything else untouched.
+///
+/// Invocations inside `macro_rules!` definitions are left alone, and so are the ones
+/// whose bodies declare metavariables elsewhere, which [`skipped_invocations`] reports.
+pub fn format_rust(source: &str) -> Result<String, FormatError> {
+    let mut formatted = source.to_string();
+    for invocation in invocations(source)?.iter().rev() {
+        if invocation.template {
+            continue;
+        }
+        if let Some((range, text)) = format_invocation(source, invocation)? {
+            formatted.replace_range(range, &text);
+        }
+    }
+    Ok(formatted)
+}
+
+/// The invocations outside of `macro_rules!` definitions that [`format_rust`] leaves
+/// alone, as their bodies declare metavariables, located at their names.
+pub fn skipped_invocations(source: &str) -> Result<Vec<FormatError>, FormatError> {
+    Ok(invocations(source)?
+        .into_iter()
+        .filter(|invocation| invocation.template)
+        .map(|invocation| {
+            FormatError::new(
+                invocation.name.span(),
+                format!(
+                    "`{}!` isn't formatted, as its body declares `macro_rules!` metavariables",
+                    invocation.name
+                ),
+            )
+        })
+        .collect())
+}
+
+fn invocations(source: &str) -> Result<Vec<Invocation>, FormatError> {
+    let mut names = POLICY_MACROS.to_vec();
+    names.push(CTX_MACRO);
+    Ok(find_invocations(lex(source)?, &names))
+}
+
+fn lex(source: &str) -> Result<TokenStream, FormatError> {
+    TokenStream::from_str(source).map_err(|err| FormatError::new(err.span(), err.to_string()))
+}
+
+/// Formats the body of a macro invocation, returning the range between its
+/// delimiters and the text to replace it with.
+fn format_invocation(
+    source: &str,
+    invocation: &Invocation,
+) -> Result<Option<(Range<usize>, String)>, FormatError> {
+    let group = &invocation.body;
+    let body = group.span_open().byte_range().end..group.span_close().byte_range().start;
+    let trees = group.stream().into_iter().collect::<Vec<_>>();
+    if trees.is_empty() {
+        return Ok(None);
+    }
+
+    let base = line_indent(source, invocation.name.span().byte_range().start);
+    let inner = format!("{base}{INDENT}");
+    let text = if invocation.name == CTX_MACRO {
+        format_ctx(
+            source,
+            &trees,
+            body.clone(),
+            group.delimiter(),
+            &inner,
+            &base,
+        )?
+    } else {
+        let lines = format_statements(source, &trees, body.clone(), &inner)?;
+        format!("\n{}\n{base}", lines.join("\n"))
+    };
+    Ok(Some((body, text)))
+}
+
+/// The whitespace at the start of the line containing byte `position`.
+fn line_indent(source: &str, position: usize) -> String {
+    let line_start = source[..position].rfind('\n').map_or(0, |index| index + 1);
+    source[line_start..]
+        .chars()
+        .take_while(|ch| *ch == ' ' || *ch == '\t')
+        .collect()
+}
+
+/// A formatted piece of a policy body, before alignment and indentation.
+enum Entry {
+    /// A one-line statement whose `right` part starts with `=>`, aligned with the
+    /// statements of the same kind around it.
+    Aligned {
+        kind: Kind,
+        left: String,
+        right: String,
+        comment: Option<String>,
+    },
+    Lines {
+        lines: Vec<String>,
+        comment: Option<String>,
+    },
+    Comment(String),
+    Blank,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Rule,
+    Expectation,
+}
+
+fn format_statements(
+    source: &str,
+    trees: &[TokenTree],
+    range: Range<usize>,
+    indent: &str,
+) -> Result<Vec<String>, FormatError> {
+    let mut entries = Vec::new();
+    let mut position = range.start;
+    for statement in split_statements(trees) {
+        let statement = without_attributes(&statement);
+        if statement.is_empty() {
+            continue;
+        }
+        let start = statement[0].span().byte_range().start;
+        let end = statement[statement.len() - 1].span().byte_range().end;
+        push_gap(
+            &source[position..start],
+            &mut entries,
+            position != range.start,
+            false,
+        );
+        entries.extend(format_statement(source, statement, start..end, indent)?);
+        position = end;
+    }
+    push_gap(
+        &source[position..range.end],
+        &mut entries,
+        position != range.start,
+        true,
+    );
+
+    Ok(render(&entries, indent))
+}
+
+/// Splits a body into statements, each ending with its top-level `;`, or with the
+/// braces of a group of rules, of a `FOR` or `WITH` block, of an `RBAC` or decision
+/// table, of a `MATCH` or of a `ROLES` declaration not followed by a `;`.
+fn split_statements(trees: &[TokenTree]) -> Vec<Vec<TokenTree>> {
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    for (index, tree) in trees.iter().enumerate() {
+        current.push(tree.clone());
+        let ends = match tree {
+            TokenTree::Punct(punct) => punct.as_char() == ';',
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+                match without_attributes(&current) {
+                    [_] => true,
+                    [TokenTree::Ident(keyword), _] if keyword == "ROLES" => !matches!(
+                        trees.get(index + 1),
+                        Some(TokenTree::Punct(punct)) if punct.as_char() == ';'
+                    ),
+                    [TokenTree::Ident(keyword), _] => keyword == "RBAC",
+                    [TokenTree::Ident(keyword), _, _]
+                        if keyword == "TABLE" || keyword == "MATCH" =>
+                    {
+                        true
+                    }
+                    [TokenTree::Ident(keyword), TokenTree::Ident(mode), _, _]
+                        if keyword == "TABLE" && mode == "COMPLETE" =>
+                    {
+                        true
+                    }
+                    [TokenTree::Ident(keyword), _, _, ..] => keyword == "FOR" || keyword == "WITH",
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        if ends {
+            statements.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        statements.push(current);
+    }
+    statements
+}
+
+/// The statement without its leading attributes. Doc comments lex into `#[doc = ".."]`
+/// attributes, so leaving them out keeps them and metadata attributes in the text
+/// between statements, where they are copied as written like any other comment.
+fn without_attributes(statement: &[TokenTree]) -> &[TokenTree] {
+    let mut rest = statement;
+    while let [TokenTree::Punct(pound), TokenTree::Group(group), tail @ ..] = rest
+        && pound.as_char() == '#'
+        && group.delimiter() == Delimiter::Bracket
+    {
+        rest = tail;
+    }
+    rest
+}
+
+/// Turns the comments and blank lines between two statements into entries. A comment
+/// on the same line as the statement before it stays attached to that statement.
+fn push_gap(gap: &str, entries: &mut Vec<Entry>, after_statement: bool, at_end: bool) {
+    let mut rest = gap;
+    let mut newlines = 0;
+    let mut first = true;
+    loop {
+        let trimmed = rest.trim_start();
+        newlines += rest[..rest.len() - trimmed.len()].matches('\n').count();
+        rest = trimmed;
+        if rest.is_empty() {
+            break;
+        }
+
+        let end = if rest.starts_with("/*") {
+            block_comment_end(rest)
+        } else {
+            rest.find('\n').unwrap_or(rest.len())
+        };
+        let comment = rest[..end].trim_end().to_string();
+        rest = &rest[end..];
+
+        let trailing = first && after_statement && newlines == 0;
+        match entries.last_mut() {
+            Some(Entry::Aligned { comment: slot, .. } | Entry::Lines { comment: slot, .. })
+                if trailing =>
+            {
+                // A comment put back from inside the statement makes way for it.
+                if let Some(inner) = slot.replace(comment) {
+                    entries.insert(entries.len() - 1, Entry::Comment(inner));
+                }
+            }
+            _ => {
+                if newlines >= 2 && !entries.is_empty() {
+                    entries.push(Entry::Blank);
+                }
+                entries.push(Entry::Comment(comment));
+            }
+        }
+        newlines = 0;
+        first = false;
+    }
+    if newlines >= 2 && !entries.is_empty() && !at_end {
+        entries.push(Entry::Blank);
+    }
+}
+
+/// The length of the (possibly nested) block comment `text` starts with.
+fn block_comment_end(text: &str) -> usize {
+    let mut depth = 0;
+    let mut index = 0;
+    while index + 1 < text.len() {
+        match &text.as_bytes()[index..index + 2] {
+            b"/*" => {
+                depth += 1;
+                index += 2;
+            }
+            b"*/" => {
+                depth -= 1;
+                index += 2;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    text.len()
+}
+
+/// Formats one statement, with the comments between its tokens put back by
+/// [`with_comments`]. Statements the formatter doesn't know are kept as written.
+fn format_statement(
+    source: &str,
+    statement: &[TokenTree],
+    range: Range<usize>,
+    indent: &str,
+) -> Result<Vec<Entry>, FormatError> {
+    if let [TokenTree::Group(group)] = statement
+        && group.delimiter() == Delimiter::Brace
+    {
+        return Ok(vec![format_group(
+            source,
+            String::from("{"),
+            group,
+            indent,
+        )?]);
+    }
+    if let [
+        TokenTree::Ident(keyword),
+        header @ ..,
+        TokenTree::Group(group),
+    ] = statement
+        && (keyword == "FOR" || keyword == "WITH")
+    {
+        let tokens = header.iter().cloned().collect();
+        let head = match header {
+            [
+                TokenTree::Ident(variable),
+                TokenTree::Ident(in_keyword),
+                TokenTree::Group(list),
+            ] if keyword == "FOR" && in_keyword == "IN" => {
+                let values = Punctuated::<LitStr, Token![,]>::parse_terminated
+                    .parse2(list.stream())?
+                    .iter()
+                    .map(|value| value.token().to_string())
+                    .collect::<Vec<_>>();
+                format!("FOR {variable} IN [{}] {{", values.join(", "))
+            }
+            _ if keyword == "FOR" => format!("FOR {} {{", target(&parse(tokens, header)?)),
+            _ => {
+                let guard: ConditionExpr = parse(tokens, header)?;
+                format!("WITH {{ {} }} {{", format_condition(&guard.expr))
+            }
+        };
+        // The comments inside the braces are formatted with the rules there.
+        let mut ranges = leaves(&statement[..statement.len() - 1]);
+        ranges.push(group.span_open().byte_range());
+        let entry = format_group(source, head, group, indent)?;
+        return Ok(with_comments(source, &ranges, entry));
+    }
+
+    let tokens = statement.iter().cloned().collect::<TokenStream>();
+    let keyword = match statement[0] {
+        TokenTree::Ident(ref ident) => ident.to_string(),
+        _ => String::new(),
+    };
+    let entry = match keyword.as_str() {
+        "ALLOW" | "DENY" => format_chain(&parse(tokens, statement)?, indent),
+        "EXPECT" => format_expectation(&parse(tokens, statement)?),
+        "CONFIG" => {
+            let tokens = statement[1..].iter().cloned().collect();
+            format_config(source, &parse(tokens, statement)?, indent)
+        }
+        "RBAC" => format_rbac(&parse(tokens, statement)?),
+        "MATCH" => format_match(&parse(tokens, statement)?, indent),
+        "TABLE" => format_table(&parse(tokens, statement)?),
+        _ => return Ok(vec![verbatim(source, range)]),
+    };
+    Ok(with_comments(source, &leaves(statement), entry))
+}
+
+/// A braced group of rules, formatted like a body of its own one level deeper below
+/// `head`, the line with the opening brace.
+fn format_group(
+    source: &str,
+    head: String,
+    group: &Group,
+    indent: &str,
+) -> Result<Entry, FormatError> {
+    let range = group.span_open().byte_range().end..group.span_close().byte_range().start;
+    let trees = group.stream().into_iter().collect::<Vec<_>>();
+    let inner = format_statements(source, &trees, range, &format!("{indent}{INDENT}"))?;
+
+    let mut lines = vec![head];
+    lines.extend(
+        inner
+            .into_iter()
+            .map(|line| match line.strip_prefix(indent) {
+                Some(line) => line.to_string(),
+                None => line,
+            }),
+    );
+    lines.push(String::from("}"));
+    Ok(Entry::Lines {
+        lines,
+        comment: None,
+    })
+}
+
+/// Parses one statement. Errors at the end of its tokens point just past its last token.
+fn parse<T: syn::parse::Parse>(
+    tokens: TokenStream,
+    statement: &[TokenTree],
+) -> Result<T, FormatError> {
+    syn::parse2(tokens).map_err(|err| {
+        if err.span().byte_range().is_empty() {
+            let end = statement[statement.len() - 1].span().end();
+            FormatError {
+                line: end.line,
+                column: end.column + 1,
+                message: err.to_string(),
+            }
+        } else {
+            err.into()
+        }
+    })
+}
+
+/// The byte ranges of the tokens of `trees` in source order, with the delimiters of
+/// groups around the tokens inside them.
+fn leaves(trees: &[TokenTree]) -> Vec<Range<usize>> {
+    fn push(trees: &[TokenTree], ranges: &mut Vec<Range<usize>>) {
+        for tree in trees {
+            if let TokenTree::Group(group) = tree {
+                ranges.push(group.span_open().byte_range());
+                push(&group.stream().into_iter().collect::<Vec<_>>(), ranges);
+                ranges.push(group.span_close().byte_range());
+            } else {
+                ranges.push(tree.span().byte_range());
+            }
+        }
+    }
+
+    let mut ranges = Vec::new();
+    push(trees, &mut ranges);
+    ranges
+}
+
+/// Whether there is anything but whitespace between the tokens of `trees`.
+fn has_inner_comment(source: &str, trees: &[TokenTree]) -> bool {
+    leaves(trees)
+        .windows(2)
+        .any(|pair| !source[pair[0].end..pair[1].start].trim().is_empty())
+}
+
+/// Puts the comments between the tokens at `ranges` back into `entry`, the statement
+/// formatted without them. Each comment follows the token it followed as written,
+/// found again in the formatted text by the token's text and how often that text
+/// occurs before it. A block comment goes right after the token, a line comment at
+/// the end of the token's line. Comments that can't be put back, as they span lines,
+/// their token was left out, or their line already ends with a comment, go on lines
+/// of their own above the statement.
+fn with_comments(source: &str, ranges: &[Range<usize>], entry: Entry) -> Vec<Entry> {
+    let mut comments = Vec::new();
+    for (index, pair) in ranges.windows(2).enumerate() {
+        let token = &source[pair[0].clone()];
+        let occurrence = ranges[..index]
+            .iter()
+            .filter(|range| source[(*range).clone()] == *token)
+            .count();
+        let mut rest = source[pair[0].end..pair[1].start].trim_start();
+        while !rest.is_empty() {
+            let end = if rest.starts_with("/*") {
+                block_comment_end(rest)
+            } else {
+                rest.find('\n').unwrap_or(rest.len())
+            };
+            comments.push((token, occurrence, rest[..end].trim_end()));
+            rest = rest[end..].trim_start();
+        }
+    }
+    if comments.is_empty() {
+        return vec![entry];
+    }
+
+    let (mut lines, mut comment, left) = match entry {
+        Entry::Aligned {
+            kind,
+            left,
+            right,
+            comment,
+        } => (
+            vec![format!("{left} {right}")],
+            comment,
+            Some((kind, left.len())),
+        ),
+        Entry::Lines { lines, comment } => (lines, comment, None),
+        Entry::Comment(_) | Entry::Blank => unreachable!("a statement is formatted into lines"),
+    };
+    let text = lines.join("\n");
+    let formatted = match lex(&text) {
+        Ok(tokens) => leaves(&tokens.into_iter().collect::<Vec<_>>()),
+        Err(_) => Vec::new(),
+    };
+
+    let mut entries = Vec::new();
+    let mut inserts = Vec::new();
+    let mut ends = vec![None; lines.len()];
+    for (token, occurrence, text_comment) in comments {
+        let found = formatted
+            .iter()
+            .filter(|range| text[(*range).clone()] == *token)
+            .nth(occurrence);
+        match found {
+            Some(range) if text_comment.starts_with("//") => {
+                let line = text[..range.end].matches('\n').count();
+                if ends[line].is_none() {
+                    ends[line] = Some(text_comment.to_string());
+                    continue;
+                }
+            }
+            Some(range) if !text_comment.contains('\n') => {
+                inserts.push((range.end, text_comment));
+                continue;
+            }
+            _ => {}
+        }
+        entries.push(Entry::Comment(text_comment.to_string()));
+    }
+
+    // Inserting from the back keeps the offsets of the earlier inserts valid.
+    inserts.sort_by_key(|&(offset, _)| std::cmp::Reverse(offset));
+    let mut text = text;
+    let mut left = left;
+    for (offset, text_comment) in inserts {
+        let spaced = match text[offset..].chars().next() {
+            Some(next) if !next.is_whitespace() => format!(" {text_comment} "),
+            _ => format!(" {text_comment}"),
+        };
+        if let Some((_, ref mut length)) = left
+            && offset <= *length
+        {
+            *length += spaced.len();
+        }
+        text.insert_str(offset, &spaced);
+    }
+    lines = text.split('\n').map(String::from).collect();
+    let last = ends.pop().flatten();
+    for (line, end) in lines.iter_mut().zip(ends) {
+        if let Some(end) = end {
+            line.push_str(&format!(" {end}"));
+        }
+    }
+    if last.is_some() {
+        comment = last;
+    }
+
+    entries.push(match left {
+        Some((kind, length)) => Entry::Aligned {
+            kind,
+            left: lines[0][..length].to_string(),
+            right: lines[0][length + 1..].to_string(),
+            comment,
+        },
+        None => Entry::Lines { lines, comment },
+    });
+    entries
+}
+
+/// The statement as written, with its lines re-indented relative to its first line.
+fn verbatim(source: &str, range: Range<usize>) -> Entry {
+    let original_indent = line_indent(source, range.start);
+    let lines = source[range]
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            let line = if index == 0 {
+                line
+            } else {
+                line.strip_prefix(original_indent.as_str())
+                    .unwrap_or(line.trim_start())
+            };
+            line.trim_end().to_string()
+        })
+        .collect();
+    Entry::Lines {
+        lines,
+        comment: None,
+    }
+}
+
+fn fits(indent: &str, line: &str) -> bool {
+    indent.chars().count() + line.chars().count() <= MAX_WIDTH
+}
+
+fn format_rule(rule: &RuleDefinition, indent: &str) -> Entry {
+    let effect = rule.effect.to_string();
+    let head = if rule.target.is_omitted() {
+        effect.clone()
+    } else {
+        format!("{effect} {}", target(&rule.target))
+    };
+    let reason = format!("=> {};", rule.reason_code);
+    let condition = rule.condition.as_ref().map(|condition| &*condition.expr);
+
+    let left = match condition {
+        Some(condition) => format!("{head} WHERE {{ {} }}", format_condition(condition)),
+        None => head.clone(),
+    };
+    if fits(indent, &format!("{left} {reason}")) {
+        return Entry::Aligned {
+            kind: Kind::Rule,
+            left,
+            right: reason,
+            comment: None,
+        };
+    }
+
+    let mut lines = Vec::new();
+    match rule.target {
+        TargetSpec::Struct(ref s) if !fits(indent, &head) => {
+            lines.push(format!("{effect} {{"));
+            for field in struct_fields(s) {
+                lines.push(format!("{INDENT}{field},"));
+            }
+            match condition {
+                Some(condition) => {
+                    let clause = format!("}} WHERE {{ {} }} {reason}", format_condition(condition));
+                    if fits(indent, &clause) {
+                        lines.push(clause);
+                    } else {
+                        lines.push(String::from("} WHERE {"));
+                        for line in condition_chain(condition) {
+                            lines.push(format!("{INDENT}{line}"));
+                        }
+                        lines.push(format!("}} {reason}"));
+                    }
+                }
+                None => lines.push(format!("}} {reason}")),
+            }
+        }
+        _ => match condition {
+            Some(condition) => {
+                lines.push(head);
+                let clause = format!(
+                    "{INDENT}WHERE {{ {} }} {reason}",
+                    format_condition(condition)
+                );
+                if fits(indent, &clause) {
+                    lines.push(clause);
+                } else {
+                    lines.push(format!("{INDENT}WHERE {{"));
+                    for line in condition_chain(condition) {
+                        lines.push(format!("{INDENT}{INDENT}{line}"));
+                    }
+                    lines.push(format!("{INDENT}}} {reason}"));
+                }
+            }
+            None => lines.push(format!("{head} {reason}")),
+        },
+    }
+
+    Entry::Lines {
+        lines,
+        comment: None,
+    }
+}
+
+/// A rule, with its `ELSE` branches on lines of their own below it.
+fn format_chain(chain: &RuleChain, indent: &str) -> Entry {
+    if chain.branches.is_empty() {
+        return format_rule(&chain.rule, indent);
+    }
+    let mut lines = rule_lines(format_rule(&chain.rule, indent));
+    if let Some(last) = lines.last_mut() {
+        last.pop();
+    }
+    for (index, branch) in chain.branches.iter().enumerate() {
+        let head = match branch.condition {
+            Some(ref condition) => format!(
+                "ELSE IF {{ {} }} {}",
+                format_condition(&condition.expr),
+                branch.effect
+            ),
+            None => format!("ELSE {}", branch.effect),
+        };
+        let end = if index + 1 == chain.branches.len() {
+            ";"
+        } else {
+            ""
+        };
+        lines.push(format!("{INDENT}{head} => {}{end}", branch.reason_code));
+    }
+    Entry::Lines {
+        lines,
+        comment: None,
+    }
+}
+
+fn format_expectation(expectation: &Expectation) -> Entry {
+    let request = &expectation.request;
+    let mut left = format!(
+        "EXPECT ({} {} {})",
+        request.principal.token(),
+        request.action.token(),
+        request.resource.token()
+    );
+    if !request.context.pairs.is_empty() {
+        let pairs = request
+            .context
+            .pairs
+            .iter()
+            .map(|(key, value)| format!("{} => {}", attr(&key.value()), format_value(value)))
+            .collect::<Vec<_>>();
+        left.push_str(&format!(" WITH {{ {} }}", pairs.join(", ")));
+    }
+
+    let mut right = format!("=> {}", expectation.expected.effect);
+    if let Some(ref reason) = expectation.expected.reason {
+        right.push_str(&format!(" {reason}"));
+    }
+    right.push(';');
+
+    Entry::Aligned {
+        kind: Kind::Expectation,
+        left,
+        right,
+        comment: None,
+    }
+}
+
+/// An `RBAC` table with one line per row, `=>` aligned across the rows.
+fn format_rbac(table: &RbacTable) -> Entry {
+    let mut lines = vec![
+        String::from("RBAC {"),
+        format!("{INDENT}attr: {};", attr(&table.attr.value())),
+    ];
+    if let Some(ref reason) = table.reason {
+        lines.push(format!("{INDENT}reason: {reason};"));
+    }
+    let roles = table
+        .rows
+        .iter()
+        .map(|row| attr(&row.role.value()))
+        .collect::<Vec<_>>();
+    let width = roles.iter().map(|role| role.chars().count()).max();
+    for (row, role) in table.rows.iter().zip(&roles) {
+        let padding = " ".repeat(width.unwrap_or(0) - role.chars().count());
+        let actions = field_value(&row.actions);
+        match row.reason {
+            Some(ref reason) => {
+                lines.push(format!("{INDENT}{role}{padding} => {actions} => {reason};"));
+            }
+            None => lines.push(format!("{INDENT}{role}{padding} => {actions};")),
+        }
+    }
+    lines.push(String::from("}"));
+    Entry::Lines {
+        lines,
+        comment: None,
+    }
+}
+
+/// The lines of a formatted rule, on their own rather than aligned with other rules.
+fn rule_lines(entry: Entry) -> Vec<String> {
+    match entry {
+        Entry::Aligned { left, right, .. } => vec![format!("{left} {right}")],
+        Entry::Lines { lines, .. } => lines,
+        Entry::Comment(_) | Entry::Blank => unreachable!("a rule is formatted into lines"),
+    }
+}
+
+/// A `MATCH` with its arms' values padded to the same width and their rules after them.
+fn format_match(block: &MatchBlock, indent: &str) -> Entry {
+    let values = block
+        .arms
+        .iter()
+        .map(|arm| match arm.value {
+            Some(ref value) => format_value(value),
+            None => String::from("_"),
+        })
+        .collect::<Vec<_>>();
+    let width = values.iter().map(|value| value.chars().count()).max();
+
+    let mut lines = vec![format!("MATCH {} {{", attr(&block.attr.value()))];
+    for (arm, value) in block.arms.iter().zip(&values) {
+        let head = format!(
+            "{INDENT}{value}{} => ",
+            " ".repeat(width.unwrap_or(0) - value.chars().count())
+        );
+        let inner = format!("{indent}{}", " ".repeat(head.chars().count()));
+        let rule = rule_lines(format_chain(&arm.chain, &inner));
+        for (index, line) in rule.into_iter().enumerate() {
+            match index {
+                0 => lines.push(format!("{head}{line}")),
+                _ => lines.push(format!("{INDENT}{line}")),
+            }
+        }
+    }
+    lines.push(String::from("}"));
+    Entry::Lines {
+        lines,
+        comment: None,
+    }
+}
+
+/// A decision `TABLE` with one line per row, `=>` aligned across the rows.
+fn format_table(table: &DecisionTable) -> Entry {
+    let values = |values: &[Value]| {
+        let values = values.iter().map(format_value).collect::<Vec<_>>();
+        format!("[{}]", values.join(", "))
+    };
+    let columns = table
+        .columns
+        .iter()
+        .map(|column| match column.values {
+            Some(ref declared) => format!("{}: {}", attr(&column.name.value()), values(declared)),
+            None => attr(&column.name.value()),
+        })
+        .collect::<Vec<_>>();
+    let rows = table
+        .rows
+        .iter()
+        .map(|row| {
+            let cells = row
+                .cells
+                .iter()
+                .map(|cell| match cell {
+                    TableCell::Any(_) => String::from("*"),
+                    TableCell::Value(value) => format_value(value),
+                    TableCell::Array(array) => values(array),
+                })
+                .collect::<Vec<_>>();
+            format!("({})", cells.join(", "))
+        })
+        .collect::<Vec<_>>();
+    let width = rows.iter().map(|row| row.chars().count()).max();
+
+    let keyword = if table.complete {
+        "TABLE COMPLETE"
+    } else {
+        "TABLE"
+    };
+    let mut lines = vec![format!("{keyword} ({}) {{", columns.join(", "))];
+    for (row, cells) in table.rows.iter().zip(&rows) {
+        let padding = " ".repeat(width.unwrap_or(0) - cells.chars().count());
+        lines.push(format!(
+            "{INDENT}{cells}{padding} => {} {};",
+            row.effect, row.reason
+        ));
+    }
+    lines.push(String::from("}"));
+    Entry::Lines {
+        lines,
+        comment: None,
+    }
+}
+
+fn format_config(source: &str, block: &ConfigBlock, indent: &str) -> Entry {
+    let fields = block
+        .fields
+        .iter()
+        .map(|field| {
+            let value = &source[field.value.span().byte_range()];
+            format!("{}: {value}", field.name)
+        })
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        return Entry::Lines {
+            lines: vec![String::from("CONFIG {};")],
+            comment: None,
+        };
+    }
+
+    let line = format!("CONFIG {{ {} }};", fields.join(", "));
+    let lines = if fits(indent, &line) {
+        vec![line]
+    } else {
+        let mut lines = vec![String::from("CONFIG {")];
+        lines.extend(fields.iter().map(|field| format!("{INDENT}{field},")));
+        lines.push(String::from("};"));
+        lines
+    };
+    Entry::Lines {
+        lines,
+        comment: None,
+    }
+}
+
+fn format_ctx(
+    source: &str,
+    trees: &[TokenTree],
+    body: Range<usize>,
+    delimiter: Delimiter,
+    inner: &str,
+    base: &str,
+) -> Result<String, FormatError> {
+    let first = trees[0].span().byte_range().start;
+    let last = trees[trees.len() - 1].span().byte_range().end;
+    if has_inner_comment(source, trees)
+        || !source[body.start..first].trim().is_empty()
+        || !source[last..body.end].trim().is_empty()
+    {
+        return Ok(source[body].to_string());
+    }
+
+    let ctx: CtxDefinition = syn::parse2(trees.iter().cloned().collect())?;
+    let pairs = ctx
+        .pairs
+        .iter()
+        .map(|(key, value)| (key.token().to_string(), format_value(value)))
+        .collect::<Vec<_>>();
+
+    if !source[body].contains('\n') {
+        let pairs = pairs
+            .iter()
+            .map(|(key, value)| format!("{key} => {value}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Ok(match delimiter {
+            Delimiter::Brace => format!(" {pairs} "),
+            _ => pairs,
+        });
+    }
+
+    let width = pairs
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+    let lines = pairs
+        .iter()
+        .map(|(key, value)| format!("{inner}{key:width$} => {value},"))
+        .collect::<Vec<_>>();
+    Ok(format!("\n{}\n{base}", lines.join("\n")))
+}
+
+/// Indents every entry and pads the left side of aligned statements to a common width.
+fn render(entries: &[Entry], indent: &str) -> Vec<String> {
+    let mut widths = vec![0; entries.len()];
+    let mut index = 0;
+    while index < entries.len() {
+        let Entry::Aligned { kind, .. } = entries[index] else {
+            index += 1;
+            continue;
+        };
+
+        let mut group = Vec::new();
+        let mut next = index;
+        while next < entries.len() {
+            match entries[next] {
+                Entry::Aligned { kind: other, .. } if other == kind => group.push(next),
+                Entry::Comment(_) => {}
+                _ => break,
+            }
+            next += 1;
+        }
+        let width = group
+            .iter()
+            .map(|&member| match entries[member] {
+                Entry::Aligned { ref left, .. } => left.chars().count(),
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0);
+        for member in group {
+            widths[member] = width;
+        }
+        index = next;
+    }
+
+    let with_comment = |line: String, comment: &Option<String>| match comment {
+        Some(comment) => format!("{line} {comment}"),
+        None => line,
+    };
+
+    let mut lines = Vec::new();
+    for (entry, width) in entries.iter().zip(widths) {
+        match entry {
+            Entry::Aligned {
+                left,
+                right,
+                comment,
+                ..
+            } => {
+                let padded = format!("{left:width$} {right}");
+                let line = if fits(indent, &padded) {
+                    padded
+                } else {
+                    format!("{left} {right}")
+                };
+                lines.push(with_comment(format!("{indent}{line}"), comment));
+            }
+            Entry::Lines {
+                lines: statement,
+                comment,
+            } => {
+                let count = statement.len();
+                for (index, line) in statement.iter().enumerate() {
+                    let line = if line.is_empty() {
+                        String::new()
+                    } else {
+                        format!("{indent}{line}")
+                    };
+                    if index + 1 == count {
+                        lines.push(with_comment(line, comment));
+                    } else {
+                        lines.push(line);
+                    }
+                }
+            }
+            Entry::Comment(comment) => {
+                for (index, line) in comment.lines().enumerate() {
+                    if index == 0 {
+                        lines.push(format!("{indent}{line}"));
+                    } else {
+                        lines.push(line.trim_end().to_string());
+                    }
+                }
+            }
+            Entry::Blank => lines.push(String::new()),
+        }
+    }
+    lines
+}
+
+fn target(target: &TargetSpec) -> String {
+    match target {
+        TargetSpec::Any(_) => String::from("ANY"),
+        TargetSpec::Tuple(t) => format!(
+            "({} {} {})",
+            field_value(&t.principal),
+            field_value(&t.action),
+            field_value(&t.resource)
+        ),
+        TargetSpec::Struct(s) => {
+            let fields = struct_fields(s);
+            if fields.is_empty() {
+                String::from("{}")
+            } else {
+                format!("{{ {} }}", fields.join(", "))
+            }
+        }
+    }
+}
+
+/// The fields of a struct target in principal, action, resource order, keeping the
+/// value that takes effect when a field is given more than once.
+fn struct_fields(target: &StructTarget) -> Vec<String> {
+    StructTargetField::valid_field_names()
+        .iter()
+        .filter_map(|name| {
+            target
+                .field(name)
+                .map(|value| format!("{name}: {}", field_value(value)))
+        })
+        .collect()
+}
+
+fn field_value(value: &FieldValue) -> String {
+    match value {
+        FieldValue::Literal(lit) => lit.token().to_string(),
+        FieldValue::Array(values) => {
+            let values = values
+                .iter()
+                .map(|lit| lit.token().to_string())
+                .collect::<Vec<_>>();
+            format!("[{}]", values.join(", "))
+        }
+        FieldValue::Any(_) => String::from("*"),
+    }
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Int(lit) => lit.token().to_string(),
+        Value::Str(lit) => lit.token().to_string(),
+        Value::Bool(lit) => lit.value.to_string(),
+        Value::Param(name) => format!("${name}"),
+    }
+}
+
+/// An attribute name as a bare identifier where that parses back to the same name.
+fn attr(name: &str) -> String {
+    const KEYWORDS: &[&str] = &["NOT", "AND", "OR", "EQ", "NEQ", "AT_LEAST", "WITH"];
+    if syn::parse_str::<syn::Ident>(name).is_ok() && !KEYWORDS.contains(&name) {
+        name.to_string()
+    } else {
+        format!("{name:?}")
+    }
+}
+
+/// Renders a condition so it parses back into the same tree. `AND` and `OR` chain to
+/// the right, so the right operand only needs parentheses when its operator differs.
+fn format_condition(condition: &Condition) -> String {
+    match condition {
+        Condition::Equals { attr: name, value } => {
+            format!("{} EQ {}", attr(name), format_value(value))
+        }
+        Condition::NotEquals { attr: name, value } => {
+            format!("{} NEQ {}", attr(name), format_value(value))
+        }
+        Condition::AtLeast {
+            attr: name, role, ..
+        } => format!("{} AT_LEAST {}", attr(name), role.token()),
+        Condition::And(..) | Condition::Or(..) => condition_chain(condition).join(" "),
+        Condition::Not(inner) => match **inner {
+            Condition::True | Condition::False => format!("NOT {}", format_condition(inner)),
+            _ => format!("NOT ({})", format_condition(inner)),
+        },
+        Condition::True => String::from("true"),
+        Condition::False => String::from("false"),
+    }
+}
+
+/// Splits a condition at its top-level chain of one operator, each operand after the
+/// first prefixed with the operator, e.g. `["a EQ 1", "AND b EQ 2", "AND c EQ 3"]`.
+fn condition_chain(condition: &Condition) -> Vec<String> {
+    fn operand(condition: &Condition) -> String {
+        match condition {
+            Condition::And(..) | Condition::Or(..) => format!("({})", format_condition(condition)),
+            _ => format_condition(condition),
+        }
+    }
+
+    let (operator, mut left, mut right) = match condition {
+        Condition::And(left, right) => ("AND", left, right),
+        Condition::Or(left, right) => ("OR", left, right),
+        _ => return vec![format_condition(condition)],
+    };
+    let mut chain = vec![operand(left)];
+    loop {
+        match (operator, &**right) {
+            ("AND", Condition::And(next_left, next_right))
+            | ("OR", Condition::Or(next_left, next_right)) => {
+                left = next_left;
+                right = next_right;
+                chain.push(format!("{operator} {}", operand(left)));
+            }
+            _ => {
+                chain.push(format!("{operator} {}", operand(right)));
+                return chain;
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! `gate0-dsl-fmt [--check] [PATH]...`
+//!
+//! Formats the policy DSL in the given `.rs` and `.gate0` files, searching
+//! directories recursively, or the current directory when no path is given.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use gate0_dsl_fmt::{FormatError, format_policy, format_rust, skipped_invocations};
+
+const USAGE: &str = "usage: gate0-dsl-fmt [--check] [PATH]...";
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                println!();
+                println!(
+                    "  --check  list the files that aren't formatted instead of rewriting them"
+                );
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option `{arg}`\n{USAGE}");
+                return ExitCode::from(2);
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let mut files = Vec::new();
+    let mut failed = false;
+    for path in &paths {
+        if let Err(err) = collect_files(path, path.is_dir(), &mut files) {
+            eprintln!("{}: {err}", path.display());
+            failed = true;
+        }
+    }
+
+    let mut unformatted = false;
+    for file in &files {
+        match format_file(file) {
+            Ok(None) => {}
+            Ok(Some(_)) if check => {
+                println!("{}", file.display());
+                unformatted = true;
+            }
+            Ok(Some(formatted)) => {
+                if let Err(err) = std::fs::write(file, formatted) {
+                    eprintln!("{}: {err}", file.display());
+                    failed = true;
+                }
+            }
+            Err(FileError::Io(err)) => {
+                eprintln!("{}: {err}", file.display());
+                failed = true;
+            }
+            Err(FileError::Format(err)) => {
+                eprintln!("{}:{err}", file.display());
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if unformatted {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Collects the files to format. Paths given explicitly are always included;
+/// inside directories only `.rs` and `.gate0` files are, skipping `target` and
+/// hidden directories.
+fn collect_files(path: &Path, walk: bool, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !walk {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        if entry.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                collect_files(&entry, true, files)?;
+            }
+        } else if matches!(
+            entry.extension().and_then(|ext| ext.to_str()),
+            Some("rs" | "gate0")
+        ) {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Formats one file, returning the new contents if they differ from the old. Lists
+/// the invocations left alone on stderr.
+fn format_file(path: &Path) -> Result<Option<String>, FileError> {
+    let source = std::fs::read_to_string(path)?;
+    let formatted = if path.extension().is_some_and(|ext| ext == "rs") {
+        for skipped in skipped_invocations(&source)? {
+            eprintln!("{}:{skipped}", path.display());
+        }
+        format_rust(&source)?
+    } else {
+        format_policy(&source)?
+    };
+    Ok((formatted != source).then_some(formatted))
+}
+
+enum FileError {
+    Io(std::io::Error),
+    Format(FormatError),
+}
+
+impl From<std::io::Error> for FileError {
+    fn from(err: std::io::Error) -> Self {
+        FileError::Io(err)
+    }
+}
+
+impl From<FormatError> for FileError {
+    fn from(err: FormatError) -> Self {
+        FileError::Format(err)
+    }
+}
@@ -0,0 +1,473 @@
+use gate0_dsl_fmt::{FormatError, format_policy, format_rust, skipped_invocations};
+
+fn assert_formats(source: &str, expected: &str) {
+    let formatted = format_policy(source).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(format_policy(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn test_align_arrows() {
+    assert_formats(
+        r#"
+ALLOW ("alice" "read" "doc1") => 1;
+DENY ("eve" * ANY)    => 2;
+// still the same group
+ALLOW * => REASON_ONE;
+
+ALLOW (["bob",   "carl"] "write" *) => 3;
+EXPECT ("alice" "read" "doc1") => ALLOW 1;
+EXPECT ("eve" "read" "doc1") WITH { "role" => "admin" } => DENY 2;
+"#,
+        r#"ALLOW ("alice" "read" "doc1") => 1;
+DENY ("eve" * *)              => 2;
+// still the same group
+ALLOW ANY                     => REASON_ONE;
+
+ALLOW (["bob", "carl"] "write" *) => 3;
+EXPECT ("alice" "read" "doc1")                        => ALLOW 1;
+EXPECT ("eve" "read" "doc1") WITH { role => "admin" } => DENY 2;
+"#,
+    );
+}
+
+#[test]
+fn test_doc_comments() {
+    assert_formats(
+        r#"
+  /// Admins can do anything.
+ALLOW ANY WHERE { role EQ "admin" } => 1;
+/// Members can read,
+/// but nothing else.
+ALLOW {action:["read"]} WHERE { role EQ "member" } => 2;
+"#,
+        r#"/// Admins can do anything.
+ALLOW ANY WHERE { role EQ "admin" }                   => 1;
+/// Members can read,
+/// but nothing else.
+ALLOW { action: ["read"] } WHERE { role EQ "member" } => 2;
+"#,
+    );
+}
+
+#[test]
+fn test_cfg_groups() {
+    assert_formats(
+        r#"
+ALLOW ANY => 1;
+#[cfg(test)] {
+  ALLOW ("bob" * *) => 2;
+  DENY ("bob" "delete" *)=>REASON;
+  #[cfg(unix)]
+  { ALLOW ("x" * *) => 3; }
+}
+"#,
+        r#"ALLOW ANY => 1;
+#[cfg(test)]
+{
+    ALLOW ("bob" * *)       => 2;
+    DENY ("bob" "delete" *) => REASON;
+    #[cfg(unix)]
+    {
+        ALLOW ("x" * *) => 3;
+    }
+}
+"#,
+    );
+}
+
+#[test]
+fn test_for_blocks() {
+    assert_formats(
+        r#"
+FOR {action: ["read","list"]}
+{
+  ALLOW WHERE {role EQ "member"} => 1;
+  DENY=>2;
+  FOR ("bob" * *) { ALLOW { action: "read" } => 3; }
+}
+"#,
+        r#"FOR { action: ["read", "list"] } {
+    ALLOW WHERE { role EQ "member" } => 1;
+    DENY                             => 2;
+    FOR ("bob" * *) {
+        ALLOW { action: "read" } => 3;
+    }
+}
+"#,
+    );
+}
+
+#[test]
+fn test_with_blocks() {
+    assert_formats(
+        r#"
+WITH {tenant_tier EQ "enterprise"} {
+ALLOW ("bob" * *) WHERE {role EQ "admin"} => 1;
+  FOR {action:"read"} { ALLOW => 2; }
+}
+"#,
+        r#"WITH { tenant_tier EQ "enterprise" } {
+    ALLOW ("bob" * *) WHERE { role EQ "admin" } => 1;
+    FOR { action: "read" } {
+        ALLOW => 2;
+    }
+}
+"#,
+    );
+}
+
+#[test]
+fn test_for_loops() {
+    assert_formats(
+        r#"
+FOR region IN ["eu","us" ,"apac"] {
+  ALLOW ("svc-$region" * *) WHERE {region EQ $region} => REGION_OK;
+}
+"#,
+        r#"FOR region IN ["eu", "us", "apac"] {
+    ALLOW ("svc-$region" * *) WHERE { region EQ $region } => REGION_OK;
+}
+"#,
+    );
+}
+
+#[test]
+fn test_role_hierarchy() {
+    assert_formats(
+        r#"
+ROLES { owner > admin > member };
+ALLOW {action:"delete"} WHERE {role AT_LEAST "admin"} => 1;
+"#,
+        r#"ROLES { owner > admin > member };
+ALLOW { action: "delete" } WHERE { role AT_LEAST "admin" } => 1;
+"#,
+    );
+    assert_formats(
+        r#"
+ROLES { owner > admin > member }
+ALLOW {action:"delete"} WHERE {role AT_LEAST "admin"} => 1;
+"#,
+        r#"ROLES { owner > admin > member }
+ALLOW { action: "delete" } WHERE { role AT_LEAST "admin" } => 1;
+"#,
+    );
+}
+
+#[test]
+fn test_rbac_tables() {
+    assert_formats(
+        r#"
+RBAC { attr: role; reason: RBAC_ALLOW;
+  admin => ANY;
+  member => ["read","list"];
+  "read-only" => ["read"] => AUDIT_READ; }
+"#,
+        r#"RBAC {
+    attr: role;
+    reason: RBAC_ALLOW;
+    admin       => *;
+    member      => ["read", "list"];
+    "read-only" => ["read"] => AUDIT_READ;
+}
+"#,
+    );
+}
+
+#[test]
+fn test_decision_tables() {
+    assert_formats(
+        r#"
+TABLE   COMPLETE (role:["admin","member"], "is-contractor", action) {
+("admin", *, ANY) => ALLOW 1;
+  ("member",false,["read","list"]) => ALLOW 2;
+}
+"#,
+        r#"TABLE COMPLETE (role: ["admin", "member"], "is-contractor", action) {
+    ("admin", *, *)                     => ALLOW 1;
+    ("member", false, ["read", "list"]) => ALLOW 2;
+}
+"#,
+    );
+}
+
+#[test]
+fn test_else_chains() {
+    assert_formats(
+        r#"
+ALLOW {action:"export"} WHERE {plan EQ "premium"} => EXPORT_OK ELSE IF {role EQ "admin"} ALLOW => 2 ELSE DENY=>EXPORT_NEEDS_PREMIUM;
+ALLOW ANY => 1;
+"#,
+        r#"ALLOW { action: "export" } WHERE { plan EQ "premium" } => EXPORT_OK
+    ELSE IF { role EQ "admin" } ALLOW => 2
+    ELSE DENY => EXPORT_NEEDS_PREMIUM;
+ALLOW ANY => 1;
+"#,
+    );
+}
+
+#[test]
+fn test_match_blocks() {
+    assert_formats(
+        r#"
+MATCH role {
+"admin" => ALLOW ANY => ADMIN;
+  "member" => ALLOW {action:["read"]} => MEMBER_READ;
+  _=>DENY ANY => NO_ROLE;
+}
+"#,
+        r#"MATCH role {
+    "admin"  => ALLOW ANY => ADMIN;
+    "member" => ALLOW { action: ["read"] } => MEMBER_READ;
+    _        => DENY ANY => NO_ROLE;
+}
+"#,
+    );
+}
+
+#[test]
+fn test_struct_targets() {
+    assert_formats(
+        r#"
+ALLOW { resource: "doc1", principal: "alice", action: ANY, } => 1;
+DENY {
+    action: "delete",
+    principal: ["mallory", "trent"],
+} => 2;
+"#,
+        r#"ALLOW { principal: "alice", action: *, resource: "doc1" }  => 1;
+DENY { principal: ["mallory", "trent"], action: "delete" } => 2;
+"#,
+    );
+}
+
+#[test]
+fn test_wrap_where() {
+    assert_formats(
+        r#"
+ALLOW { principal: "alice", action: "read" } WHERE { department EQ "engineering" AND clearance EQ 3 } => 1;
+DENY ANY WHERE { NOT (department EQ "engineering") AND (region EQ "eu" OR region EQ "us") AND clearance_level EQ 3 AND "is-contractor" EQ true } => REASON_ONE;
+"#,
+        r#"ALLOW { principal: "alice", action: "read" }
+    WHERE { department EQ "engineering" AND clearance EQ 3 } => 1;
+DENY ANY
+    WHERE {
+        NOT (department EQ "engineering")
+        AND (region EQ "eu" OR region EQ "us")
+        AND clearance_level EQ 3
+        AND "is-contractor" EQ true
+    } => REASON_ONE;
+"#,
+    );
+}
+
+#[test]
+fn test_keep_comments() {
+    assert_formats(
+        r#"
+// Admins can do anything.
+ALLOW ANY WHERE { role EQ "admin" } => 1; // trailing
+
+/* Block
+   comment */
+DENY ("eve" /* never */ * *) => 2;
+CONFIG { max_rules: 10, max_condition_depth: 4 };
+"#,
+        r#"// Admins can do anything.
+ALLOW ANY WHERE { role EQ "admin" } => 1; // trailing
+
+/* Block
+   comment */
+DENY ("eve" /* never */ * *) => 2;
+CONFIG { max_rules: 10, max_condition_depth: 4 };
+"#,
+    );
+}
+
+#[test]
+fn test_inner_comments() {
+    assert_formats(
+        r#"
+DENY ("eve"   /* never */ *   *) => 2;
+
+ALLOW ANY WHERE {role EQ "admin" // admins
+    OR role EQ "owner"} => 3; // trailing
+TABLE (role, action) {
+("admin", *) => ALLOW 1; // everything
+  ("member","read") => ALLOW 2;
+}
+FOR {action:"read"} /* reads */ {
+ALLOW => 4;
+}
+ALLOW ANY /* spans
+lines */ => 5;
+"#,
+        r#"DENY ("eve" /* never */ * *) => 2;
+
+// admins
+ALLOW ANY WHERE { role EQ "admin" OR role EQ "owner" } => 3; // trailing
+TABLE (role, action) {
+    ("admin", *)       => ALLOW 1; // everything
+    ("member", "read") => ALLOW 2;
+}
+FOR { action: "read" } /* reads */ {
+    ALLOW => 4;
+}
+/* spans
+lines */
+ALLOW ANY => 5;
+"#,
+    );
+}
+
+#[test]
+fn test_format_rust() {
+    let source = r#"
+fn policy() -> gate0::Policy {
+    let ctx = ctx! {"role"=>"admin",   "level" => 3};
+    policy_builder! {
+        ALLOW ("alice" "read" "doc1") => 1;
+      DENY ANY => 2;
+    }
+    .build()
+    .unwrap()
+}
+
+macro_rules! wrapped {
+    ($($t:tt)*) => { policy_builder! { $($t)* } };
+}
+
+policy_catalog! {
+    static CATALOG;
+    /// Admins can do anything.
+    #[id = "admin"]
+    ALLOW ANY WHERE {role EQ "admin"} => 1;
+}
+"#;
+    let expected = r#"
+fn policy() -> gate0::Policy {
+    let ctx = ctx! { "role" => "admin", "level" => 3 };
+    policy_builder! {
+        ALLOW ("alice" "read" "doc1") => 1;
+        DENY ANY                      => 2;
+    }
+    .build()
+    .unwrap()
+}
+
+macro_rules! wrapped {
+    ($($t:tt)*) => { policy_builder! { $($t)* } };
+}
+
+policy_catalog! {
+    static CATALOG;
+    /// Admins can do anything.
+    #[id = "admin"]
+    ALLOW ANY WHERE { role EQ "admin" } => 1;
+}
+"#;
+    let formatted = format_rust(source).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(format_rust(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn test_format_rust_params() {
+    let source = r#"
+policy_fn! {
+    pub fn tenant_policy;
+    PARAMS(tenant: &'static str, min_level: i64);
+  ALLOW {action:"read"} WHERE {tenant EQ $tenant} => 1;
+    ALLOW ANY WHERE {tenant EQ $tenant AND level EQ $min_level} => 2;
+}
+
+fn wrapped() -> gate0::PolicyBuilder {
+    policy_builder! { $($rule:tt)* }
+}
+"#;
+    let expected = r#"
+policy_fn! {
+    pub fn tenant_policy;
+    PARAMS(tenant: &'static str, min_level: i64);
+    ALLOW { action: "read" } WHERE { tenant EQ $tenant }          => 1;
+    ALLOW ANY WHERE { tenant EQ $tenant AND level EQ $min_level } => 2;
+}
+
+fn wrapped() -> gate0::PolicyBuilder {
+    policy_builder! { $($rule:tt)* }
+}
+"#;
+    assert_eq!(format_rust(source).unwrap(), expected);
+    assert_eq!(
+        skipped_invocations(source).unwrap(),
+        [FormatError {
+            line: 10,
+            column: 5,
+            message: String::from(
+                "`policy_builder!` isn't formatted, as its body declares `macro_rules!` metavariables"
+            ),
+        }]
+    );
+}
+
+#[test]
+fn test_format_rust_companions() {
+    // The lines declaring companion items are kept as written, like the signature.
+    let source = r#"
+policy_fn! {
+    pub fn saas_policy;
+    CATALOG   pub static SAAS_RULES;
+    COVERAGE static SAAS_COVERAGE:SaasCoverage;
+  ALLOW ANY WHERE {role EQ "admin"} => 1;
+}
+"#;
+    let expected = r#"
+policy_fn! {
+    pub fn saas_policy;
+    CATALOG   pub static SAAS_RULES;
+    COVERAGE static SAAS_COVERAGE:SaasCoverage;
+    ALLOW ANY WHERE { role EQ "admin" } => 1;
+}
+"#;
+    assert_eq!(format_rust(source).unwrap(), expected);
+}
+
+#[test]
+fn test_format_rust_for_loops() {
+    let source = r#"
+fn regions() -> gate0::PolicyBuilder {
+    policy_builder! {
+        FOR region IN ["eu","us"] {
+          ALLOW ("svc-$region" * *) WHERE {region EQ $region} => 1;
+        }
+    }
+}
+"#;
+    let expected = r#"
+fn regions() -> gate0::PolicyBuilder {
+    policy_builder! {
+        FOR region IN ["eu", "us"] {
+            ALLOW ("svc-$region" * *) WHERE { region EQ $region } => 1;
+        }
+    }
+}
+"#;
+    assert_eq!(format_rust(source).unwrap(), expected);
+    assert_eq!(skipped_invocations(source).unwrap(), []);
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(
+        format_policy("ALLOW ANY => 1;\nALLOW NONE => 2;"),
+        Err(FormatError {
+            line: 2,
+            column: 7,
+            message: String::from("expected ANY, *, tuple, or struct"),
+        })
+    );
+    assert_eq!(
+        format_policy("ALLOW ANY => 1;\nALLOW ANY => 2").map_err(|err| err.to_string()),
+        Err(String::from("2:15: expected `;`"))
+    );
+}
@@ -1,16 +1,15 @@
 //! This is crate provides a macro for [gate0](https://github.com/Qarait/gate0) to simplify the creation of gate0 policies, using a simple DSL, and a macro for creating contexts.
 
-use proc_macro::TokenStream;
-use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::{ToTokens, quote};
-use syn::{
-    Expr, Ident, LitBool, LitInt, LitStr, Result, Token,
-    parse::{Parse, ParseStream},
-    parse_macro_input,
-    token::{Brace, Paren},
+use gate0_dsl_core::{
+    ast::{
+        AssertDecision, CtxDefinition, IncludePolicy, PolicyCatalog, PolicyCoverage,
+        PolicyDefinition, PolicyExplain, PolicyFn, PolicyTests,
+    },
+    codegen::DecisionMatrix,
+    validate::{Diagnostic, Usage, validate_fn, validate_usage},
 };
-
-const VALID_INT_TYPES_TEXT: &str = "&str, bool, i8, i16, i32, i64, u8, u16, u32";
+use proc_macro::TokenStream;
+use syn::parse_macro_input;
 
 /// ```
 /// // Can create a context with key-value pairs
@@ -30,785 +29,238 @@ pub fn ctx(input: TokenStream) -> TokenStream {
 #[proc_macro]
 pub fn policy_builder(input: TokenStream) -> TokenStream {
     let policy_def = parse_macro_input!(input as PolicyDefinition);
-    let expanded = policy_def.expand();
-    TokenStream::from(expanded)
-}
-
-struct CtxDefinition {
-    pairs: Vec<(syn::LitStr, Value)>,
-}
-
-impl Parse for CtxDefinition {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let mut pairs = Vec::new();
-
-        while !input.is_empty() {
-            let key: syn::LitStr = input.parse()?;
-            input.parse::<Token![=>]>()?;
-            let value: Value = input.parse()?;
-            pairs.push((key, value));
-
-            if !input.is_empty() {
-                input.parse::<Token![,]>()?;
-            }
-        }
-
-        Ok(CtxDefinition { pairs })
-    }
-}
-
-impl CtxDefinition {
-    fn expand(&self) -> TokenStream2 {
-        let pairs = &self.pairs;
-        let keys = pairs.iter().map(|(k, _)| k);
-        let values = pairs.iter().map(|(_, v)| v.expand());
-
-        quote! {
-            &{
-                let context: [(&str, ::gate0::Value); _] = [
-                    #(
-                        (#keys, #values),
-                    )*
-                ];
-                context
-            }
-        }
-    }
-}
-
-struct PolicyDefinition {
-    config: Option<ConfigBlock>,
-    use_builder: Option<Expr>,
-    rules: Vec<RuleDefinition>,
-}
-
-impl Parse for PolicyDefinition {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let mut config = None;
-        let mut use_builder = None;
-        let mut rules = Vec::new();
-
-        if input.peek(Ident) {
-            let lookahead = input.fork();
-            if let Ok(ident) = lookahead.parse::<Ident>()
-                && ident == "USE"
-            {
-                let _: Ident = input.parse()?;
-                let builder_expr: Expr = input.parse()?;
-                input.parse::<Token![;]>()?;
-                use_builder = Some(builder_expr);
-            }
-        }
-
-        if input.peek(Ident) {
-            let lookahead = input.fork();
-            if let Ok(ident) = lookahead.parse::<Ident>()
-                && ident == "CONFIG"
-            {
-                let _: Ident = input.parse()?;
-                config = Some(input.parse()?);
-            }
-        }
-
-        while !input.is_empty() {
-            rules.push(input.parse()?);
-        }
-
-        Ok(PolicyDefinition {
-            config,
-            use_builder,
-            rules,
-        })
-    }
-}
-
-impl PolicyDefinition {
-    fn expand(&self) -> TokenStream2 {
-        let builder_init = if let Some(ref builder_expr) = self.use_builder {
-            quote! { let mut builder = #builder_expr; }
-        } else {
-            quote! { let mut builder = ::gate0::Policy::builder(); }
-        };
-
-        let config_setup = if let Some(ref cfg) = self.config {
-            let config_fields = &cfg.fields;
-            quote! {
-                builder = builder.config(::gate0::PolicyConfig {
-                    #(#config_fields)*
-                    ..::gate0::PolicyConfig::default()
-                });
-            }
-        } else {
-            quote! {}
-        };
-
-        let rule_additions = self.rules.iter().map(|rule| {
-            let rule_expr = rule.expand();
-            quote! {
-                builder = builder.rule(#rule_expr);
-            }
-        });
-
-        quote! {
-            {
-                #builder_init
-                #config_setup
-                #(#rule_additions)*
-                builder
-            }
-        }
-    }
-}
-
-struct ConfigBlock {
-    fields: Vec<ConfigField>,
-}
-
-impl Parse for ConfigBlock {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let content;
-        syn::braced!(content in input);
-        input.parse::<Token![;]>()?;
-
-        let mut fields = Vec::new();
-        while !content.is_empty() {
-            let name: Ident = content.parse()?;
-            content.parse::<Token![:]>()?;
-            let value: Expr = content.parse()?;
-            fields.push(ConfigField { name, value });
-
-            if !content.is_empty() {
-                content.parse::<Token![,]>()?;
-            }
-        }
-
-        Ok(ConfigBlock { fields })
+    if let Err(err) = check(validate_usage(&policy_def, Usage::Builder)) {
+        return err.to_compile_error().into();
     }
+    let expanded = policy_def.expand_checked();
+    TokenStream::from(expanded)
 }
 
-struct ConfigField {
-    name: Ident,
-    value: Expr,
+/// ```rs
+/// // Reads a policy file relative to CARGO_MANIFEST_DIR at compile time
+/// let builder = include_policy!("policies/api.gate0");
+/// ```
+///
+/// The file uses the same grammar as `policy_builder!` and expands the same way, with
+/// identifiers resolved where the macro is invoked. Errors point at the path, as tokens
+/// parsed inside a proc macro carry no position in the file; `gate0-dsl check` reports
+/// their line and column. Edits to the file trigger a rebuild.
+#[proc_macro]
+pub fn include_policy(input: TokenStream) -> TokenStream {
+    let include_def = parse_macro_input!(input as IncludePolicy);
+    let expanded = include_def.expand();
+    TokenStream::from(expanded)
 }
 
-impl ToTokens for ConfigField {
-    fn to_tokens(&self, tokens: &mut TokenStream2) {
-        let name = &self.name;
-        let value = &self.value;
-        tokens.extend(quote! { #name: #value, });
-    }
+/// ```rs
+/// // Generates one #[test] per row, evaluating the request against the policy
+/// policy_tests! {
+///     POLICY <expression evaluating to a gate0::Policy>;
+///
+///     [<test_name>:] <principal> <action> <resource> [{ <attr> => <value>, ... }] => <ALLOW|DENY>[(<reason_code>)];
+///     ...
+/// }
+/// ```
+///
+/// Rows without an explicit name are named after their request, e.g.
+/// `"bob" "update" "doc-123" { "role" => "member" }` becomes `bob_update_doc_123_role_member`.
+#[proc_macro]
+pub fn policy_tests(input: TokenStream) -> TokenStream {
+    let tests_def = parse_macro_input!(input as PolicyTests);
+    let expanded = tests_def.expand();
+    TokenStream::from(expanded)
 }
 
-struct RuleDefinition {
-    effect: Effect,
-    target: TargetSpec,
-    condition: Option<ConditionExpr>,
-    reason_code: ReasonCode,
+/// ```rs
+/// // Panics with the request, context, expected and actual decision on mismatch
+/// assert_decision!(<policy>, <principal> <action> <resource> [{ <attr> => <value>, ... }] => <ALLOW|DENY>[(<reason_code>)]);
+/// ```
+///
+/// The failure message also lists the rules whose targets match the request: as written
+/// in the DSL when `<policy>` is written inline with `policy_builder!`, and otherwise as
+/// read from the built policy's `Debug` output.
+#[proc_macro]
+pub fn assert_decision(input: TokenStream) -> TokenStream {
+    let assertion = parse_macro_input!(input as AssertDecision);
+    let expanded = assertion.expand();
+    TokenStream::from(expanded)
 }
 
-impl Parse for RuleDefinition {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let effect: Effect = input.parse()?;
-        let target: TargetSpec = input.parse()?;
-
-        let condition = if input.peek(Ident) {
-            let lookahead = input.fork();
-            if let Ok(ident) = lookahead.parse::<Ident>() {
-                if ident == "WHERE" {
-                    let _: Ident = input.parse()?;
-                    Some(input.parse()?)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        input.parse::<Token![=>]>()?;
-        let reason_code: ReasonCode = input.parse()?;
-        input.parse::<Token![;]>()?;
-
-        Ok(RuleDefinition {
-            effect,
-            target,
-            condition,
-            reason_code,
-        })
+/// ```rs
+/// // Takes the same input as policy_builder! and evaluates to a String table
+/// insta::assert_snapshot!(decision_matrix! {
+///     <rules>
+/// });
+/// ```
+///
+/// Every literal principal, action and resource, and every attribute/value pair
+/// compared in a condition, is collected together with an `<other>` sentinel.
+/// The policy is evaluated for the full cross product, so the number of rows grows
+/// quickly with the number of attributes.
+#[proc_macro]
+pub fn decision_matrix(input: TokenStream) -> TokenStream {
+    let policy_def = parse_macro_input!(input as PolicyDefinition);
+    if let Err(err) = check(validate_usage(&policy_def, Usage::Derived)) {
+        return err.to_compile_error().into();
     }
+    let expanded = DecisionMatrix::new(&policy_def).expand(&policy_def);
+    TokenStream::from(expanded)
 }
 
-impl RuleDefinition {
-    fn expand(&self) -> TokenStream2 {
-        let effect = self.effect.expand();
-        let target = self.target.expand();
-        let condition = if let Some(ref cond) = self.condition {
-            let cond_expr = cond.expand();
-            quote! { Some(#cond_expr) }
-        } else {
-            quote! { None }
-        };
-        let reason = self.reason_code.expand();
-
-        quote! {
-            ::gate0::Rule::new(
-                #effect,
-                #target,
-                #condition,
-                #reason
-            )
-        }
+/// ```rs
+/// // Takes the same input as policy_builder! and evaluates to a &'static str
+/// const POLICY_DOC: &str = policy_doc! {
+///     /// <description>
+///     <rule>
+///     ...
+/// };
+/// ```
+///
+/// The rules are rendered to a Markdown table at compile time, with their effect,
+/// principals, actions, resources, conditions in plain language and reason code. The
+/// doc comments written on a rule become its description. `DOC` in `policy_fn!` declares
+/// the same string as a constant next to the builder, without repeating the rules.
+#[proc_macro]
+pub fn policy_doc(input: TokenStream) -> TokenStream {
+    let policy_def = parse_macro_input!(input as PolicyDefinition);
+    if let Err(err) = check(validate_usage(&policy_def, Usage::Derived)) {
+        return err.to_compile_error().into();
     }
+    let expanded = policy_def.expand_doc();
+    TokenStream::from(expanded)
 }
 
-enum Effect {
-    Allow,
-    Deny,
-}
-
-impl Parse for Effect {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let ident: Ident = input.parse()?;
-        match ident.to_string().as_str() {
-            "ALLOW" => Ok(Effect::Allow),
-            "DENY" => Ok(Effect::Deny),
-            _ => Err(syn::Error::new(ident.span(), "expected ALLOW or DENY")),
-        }
+/// ```rs
+/// // Declares a static tracker counting which rules match recorded requests
+/// policy_coverage! {
+///     [pub] static <NAME>: <TypeName>;
+///     <rules>
+/// }
+///
+/// let decision = NAME.evaluate(&request)?;
+/// NAME.assert_covered();
+/// ```
+///
+/// The rules take the same input as `policy_builder!`, and the tracker builds the policy
+/// they make: `TypeName::builder()` returns its builder, `NAME.policy()` the built policy,
+/// and `NAME.evaluate` records a request before evaluating it. Each rule is remembered
+/// with its file, line, reason code and DSL text, and matches are counted per rule index
+/// by evaluating the request against every rule on its own. `COVERAGE` in `policy_fn!`
+/// declares the tracker next to the function building the policy.
+#[proc_macro]
+pub fn policy_coverage(input: TokenStream) -> TokenStream {
+    let coverage_def = parse_macro_input!(input as PolicyCoverage);
+    if let Err(err) = check(validate_usage(&coverage_def.policy, Usage::Derived)) {
+        return err.to_compile_error().into();
     }
+    let expanded = coverage_def.expand();
+    TokenStream::from(expanded)
 }
 
-impl Effect {
-    fn expand(&self) -> TokenStream2 {
-        match self {
-            Effect::Allow => quote! { ::gate0::Effect::Allow },
-            Effect::Deny => quote! { ::gate0::Effect::Deny },
-        }
+/// ```rs
+/// // Declares a static catalog of rule metadata, one entry per rule in rule order
+/// policy_catalog! {
+///     [pub] static <NAME>;
+///     /// <description>
+///     #[id = "<id>"]
+///     #[owner = "<owner>"]
+///     <rule>
+///     ...
+/// }
+///
+/// let meta: &gate0_dsl_core::meta::RuleMeta = &NAME[index];
+/// ```
+///
+/// The rules take the same input as `policy_builder!`, where doc comments and
+/// `#[name = "value"]` attributes are accepted on every rule. An attribute can only be
+/// given once per rule, and an `id` only once per policy. Needs `gate0_dsl_core` as a
+/// dependency. The same catalog is returned by `catalog()` on what `policy_builder!`
+/// evaluates to for rules with attributes, and declared by `CATALOG` in `policy_fn!`.
+#[proc_macro]
+pub fn policy_catalog(input: TokenStream) -> TokenStream {
+    let catalog_def = parse_macro_input!(input as PolicyCatalog);
+    if let Err(err) = check(validate_usage(&catalog_def.policy, Usage::Derived)) {
+        return err.to_compile_error().into();
     }
+    let expanded = catalog_def.expand();
+    TokenStream::from(expanded)
 }
 
-enum TargetSpec {
-    #[allow(dead_code)]
-    Any(Ident),
-    Tuple(TupleTarget),
-    Struct(StructTarget),
-}
-
-impl Parse for TargetSpec {
-    fn parse(input: ParseStream) -> Result<Self> {
-        if input.peek(Token![*]) {
-            input.parse::<Token![*]>()?;
-            Ok(TargetSpec::Any(Ident::new("star", Span::call_site())))
-        } else if input.peek(Ident) {
-            let ident: Ident = input.parse()?;
-            if ident == "ANY" {
-                Ok(TargetSpec::Any(ident))
-            } else {
-                Err(syn::Error::new(
-                    ident.span(),
-                    "expected ANY, *, tuple, or struct",
-                ))
-            }
-        } else if input.peek(Paren) {
-            Ok(TargetSpec::Tuple(input.parse()?))
-        } else if input.peek(Brace) {
-            Ok(TargetSpec::Struct(input.parse()?))
-        } else {
-            Err(syn::Error::new(
-                input.span(),
-                "expected *, ANY, tuple, or struct",
-            ))
-        }
+/// ```rs
+/// // Declares a function explaining how the policy decides a request
+/// policy_explain! {
+///     [pub] fn <name>;
+///     <rules>
+/// }
+///
+/// let explanation = name(&request);
+/// println!("{explanation}");
+/// ```
+///
+/// The rules take the same input as `policy_builder!`. The returned
+/// `gate0_dsl_core::explain::Explanation` holds the policy's decision and, for every
+/// rule, whether each target field matched and the result of each node of its `WHERE`
+/// tree along with the context values compared, all in DSL syntax. The policy is built
+/// on the first call. Needs the `explain` feature of `gate0_dsl_core`. `EXPLAIN` in
+/// `policy_fn!` declares the function next to the one building the policy.
+#[proc_macro]
+pub fn policy_explain(input: TokenStream) -> TokenStream {
+    let explain_def = parse_macro_input!(input as PolicyExplain);
+    if let Err(err) = check(validate_usage(&explain_def.policy, Usage::Derived)) {
+        return err.to_compile_error().into();
     }
+    let expanded = explain_def.expand();
+    TokenStream::from(expanded)
 }
 
-impl TargetSpec {
-    fn expand(&self) -> TokenStream2 {
-        match self {
-            TargetSpec::Any(_) => quote! { ::gate0::Target::any() },
-            TargetSpec::Tuple(t) => t.expand(),
-            TargetSpec::Struct(s) => s.expand(),
-        }
+/// ```rs
+/// // Declares a function building the policy from runtime values
+/// policy_fn! {
+///     [pub] fn <name>;
+///     [CATALOG [pub] static <NAME>;]
+///     [DOC [pub] const <NAME>;]
+///     [COVERAGE [pub] static <NAME>: <TypeName>;]
+///     [EXPLAIN [pub] fn <name>;]
+///     PARAMS(<param>: <type>, ...);
+///     <rules>
+/// }
+///
+/// let builder: gate0::PolicyBuilder = name(<values>);
+/// ```
+///
+/// The rules take the same input as `policy_builder!`, and `WHERE` clauses can compare
+/// attributes with a parameter as `<attr> EQ $<param>`. Parameters can be string slices
+/// with a `'static` lifetime, booleans and integers converting into `i64`. Using a
+/// parameter that isn't declared, or declaring one that isn't used, is an error.
+///
+/// `EXPECT` lines are checked by a `#[test]` named `<name>_expectations`, generated
+/// next to the function in test builds. They aren't accepted together with `PARAMS`, as
+/// their outcome depends on the values passed in.
+///
+/// The optional lines after the function declare the items `policy_catalog!`,
+/// `policy_doc!`, `policy_coverage!` and `policy_explain!` would generate from the same
+/// rules, with the tracker and the explain function building the policy by calling
+/// `<name>()`. Those two aren't accepted together with `PARAMS`.
+#[proc_macro]
+pub fn policy_fn(input: TokenStream) -> TokenStream {
+    let fn_def = parse_macro_input!(input as PolicyFn);
+    if let Err(err) = check(validate_fn(&fn_def)) {
+        return err.to_compile_error().into();
     }
+    let expanded = fn_def.expand();
+    TokenStream::from(expanded)
 }
 
-struct TupleTarget {
-    principal: FieldValue,
-    action: FieldValue,
-    resource: FieldValue,
-}
-
-impl Parse for TupleTarget {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let content;
-        syn::parenthesized!(content in input);
-
-        let principal = content.parse()?;
-        let action = content.parse()?;
-        let resource = content.parse()?;
-
-        Ok(TupleTarget {
-            principal,
-            action,
-            resource,
+/// Combines the errors among the diagnostics into one `syn::Error`. Warnings are left
+/// to tools, as proc macros can't emit them on stable.
+fn check(diagnostics: Vec<Diagnostic>) -> syn::Result<()> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.is_error())
+        .map(Diagnostic::to_syn_error)
+        .reduce(|mut combined, err| {
+            combined.combine(err);
+            combined
         })
-    }
-}
-
-impl TupleTarget {
-    fn expand(&self) -> TokenStream2 {
-        let principal = self.principal.to_matcher();
-        let action = self.action.to_matcher();
-        let resource = self.resource.to_matcher();
-
-        quote! {
-            ::gate0::Target {
-                principal: #principal,
-                action: #action,
-                resource: #resource,
-            }
-        }
-    }
-}
-
-struct StructTarget {
-    fields: Vec<StructTargetField>,
-}
-
-impl Parse for StructTarget {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let content;
-        syn::braced!(content in input);
-
-        let mut fields = Vec::new();
-        while !content.is_empty() {
-            fields.push(content.parse()?);
-            if !content.is_empty() {
-                content.parse::<Token![,]>()?;
-            }
-        }
-
-        Ok(StructTarget { fields })
-    }
-}
-
-impl StructTarget {
-    fn expand(&self) -> TokenStream2 {
-        let mut principal = None;
-        let mut action = None;
-        let mut resource = None;
-
-        for field in &self.fields {
-            match field.name.to_string().as_str() {
-                "principal" => principal = Some(&field.value),
-                "action" => action = Some(&field.value),
-                "resource" => resource = Some(&field.value),
-                _ => {}
-            }
-        }
-
-        let principal_matcher =
-            principal.map_or_else(|| quote! { ::gate0::Matcher::Any }, FieldValue::to_matcher);
-        let action_matcher =
-            action.map_or_else(|| quote! { ::gate0::Matcher::Any }, FieldValue::to_matcher);
-        let resource_matcher =
-            resource.map_or_else(|| quote! { ::gate0::Matcher::Any }, FieldValue::to_matcher);
-
-        quote! {
-            ::gate0::Target {
-                principal: #principal_matcher,
-                action: #action_matcher,
-                resource: #resource_matcher,
-            }
-        }
-    }
-}
-
-struct StructTargetField {
-    name: Ident,
-    value: FieldValue,
-}
-
-impl StructTargetField {
-    fn valid_field_names() -> &'static [&'static str] {
-        &["principal", "action", "resource"]
-    }
-}
-
-impl Parse for StructTargetField {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let name: Ident = input.parse()?;
-        let valid_field_names = Self::valid_field_names();
-        if !valid_field_names.contains(&name.to_string().as_str()) {
-            return Err(syn::Error::new(
-                name.span(),
-                format!("expected one of: {}", valid_field_names.join(", ")),
-            ));
-        }
-        input.parse::<Token![:]>()?;
-        let value: FieldValue = input.parse()?;
-        Ok(StructTargetField { name, value })
-    }
-}
-
-enum FieldValue {
-    Literal(LitStr),
-    Array(Vec<LitStr>),
-    #[allow(dead_code)]
-    Any(Ident),
-}
-
-impl Parse for FieldValue {
-    fn parse(input: ParseStream) -> Result<Self> {
-        if input.peek(Token![*]) {
-            input.parse::<Token![*]>()?;
-            Ok(FieldValue::Any(Ident::new("star", Span::call_site())))
-        } else if input.peek(Ident) {
-            let ident: Ident = input.parse()?;
-            if ident == "ANY" {
-                Ok(FieldValue::Any(ident))
-            } else {
-                Err(syn::Error::new(
-                    ident.span(),
-                    "expected ANY, *, a string literal, or an array",
-                ))
-            }
-        } else if input.peek(syn::token::Bracket) {
-            let content;
-            syn::bracketed!(content in input);
-            let mut values = Vec::new();
-            while !content.is_empty() {
-                values.push(content.parse()?);
-                if !content.is_empty() {
-                    content.parse::<Token![,]>()?;
-                }
-            }
-            Ok(FieldValue::Array(values))
-        } else {
-            Ok(FieldValue::Literal(input.parse()?))
-        }
-    }
-}
-
-impl FieldValue {
-    fn to_matcher(&self) -> TokenStream2 {
-        match self {
-            FieldValue::Literal(lit) => quote! { ::gate0::Matcher::Exact(#lit) },
-            FieldValue::Array(arr) => quote! { ::gate0::Matcher::OneOf(&[#(#arr),*]) },
-            FieldValue::Any(_) => quote! { ::gate0::Matcher::Any },
-        }
-    }
-}
-
-enum ReasonCode {
-    Literal(LitInt),
-    Ident(Ident),
-}
-
-impl Parse for ReasonCode {
-    fn parse(input: ParseStream) -> Result<Self> {
-        if input.peek(LitInt) {
-            Ok(ReasonCode::Literal(input.parse()?))
-        } else {
-            Ok(ReasonCode::Ident(input.parse()?))
-        }
-    }
-}
-
-impl ReasonCode {
-    fn expand(&self) -> TokenStream2 {
-        match self {
-            ReasonCode::Literal(lit) => quote! { ::gate0::ReasonCode(#lit) },
-            ReasonCode::Ident(ident) => quote! { #ident },
-        }
-    }
-}
-
-struct ConditionExpr {
-    expr: Box<Condition>,
-}
-
-impl Parse for ConditionExpr {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let content;
-        syn::braced!(content in input);
-        let expr = Box::new(content.parse()?);
-        Ok(ConditionExpr { expr })
-    }
-}
-
-impl ConditionExpr {
-    fn expand(&self) -> TokenStream2 {
-        self.expr.expand()
-    }
-}
-
-enum Condition {
-    Equals { attr: String, value: Value },
-    NotEquals { attr: String, value: Value },
-    And(Box<Condition>, Box<Condition>),
-    Or(Box<Condition>, Box<Condition>),
-    Not(Box<Condition>),
-    True,
-    False,
-}
-
-impl Parse for Condition {
-    fn parse(input: ParseStream) -> Result<Self> {
-        parse_condition_inner(input)
-    }
-}
-
-fn parse_condition_inner(input: ParseStream) -> Result<Condition> {
-    if input.peek(Ident) {
-        let lookahead = input.fork();
-        if let Ok(ident) = lookahead.parse::<Ident>()
-            && ident == "NOT"
-        {
-            let _: Ident = input.parse()?;
-            let condition = Condition::Not(Box::new(parse_atom(&input)?));
-
-            return parse_rhs_condition(input, condition);
-        }
-    }
-
-    if let Some(attr) = parse_attr(input)
-        && input.peek(Ident)
-    {
-        let lookahead = input.fork();
-        if let Ok(ident) = lookahead.parse::<Ident>()
-            && let Some(lhs) = match ident.to_string().as_str() {
-                "EQ" => {
-                    let _: Ident = input.parse()?;
-                    let value: Value = input.parse()?;
-                    Some(Condition::Equals { attr, value })
-                }
-                "NEQ" => {
-                    let _: Ident = input.parse()?;
-                    let value: Value = input.parse()?;
-                    Some(Condition::NotEquals { attr, value })
-                }
-                _ => None,
-            }
-        {
-            return parse_rhs_condition(input, lhs);
-        }
-    }
-
-    let left = parse_atom(input)?;
-
-    if input.peek(Ident) {
-        let lookahead = input.fork();
-        if let Ok(ident) = lookahead.parse::<Ident>() {
-            let condition = match ident.to_string().as_str() {
-                "AND" => {
-                    let _: Ident = input.parse()?;
-                    let right = Box::new(parse_condition_inner(input)?);
-                    Condition::And(Box::new(left), right)
-                }
-                "OR" => {
-                    let _: Ident = input.parse()?;
-                    let right = Box::new(parse_condition_inner(input)?);
-                    Condition::Or(Box::new(left), right)
-                }
-                _ => {
-                    return Err(syn::Error::new(
-                        input.span(),
-                        "expected one of: EQ, NEQ, AND, OR",
-                    ));
-                }
-            };
-            if let Ok(ident) = input.parse::<Ident>() {
-                match ident.to_string().as_str() {
-                    "AND" => {
-                        let right = Box::new(parse_condition_inner(input)?);
-                        return Ok(Condition::And(Box::new(condition), right));
-                    }
-                    "OR" => {
-                        let right = Box::new(parse_condition_inner(input)?);
-                        return Ok(Condition::Or(Box::new(condition), right));
-                    }
-                    _ => {
-                        return Err(syn::Error::new(input.span(), "expected one of: AND, OR"));
-                    }
-                }
-            }
-            return Ok(condition);
-        }
-    }
-
-    Ok(left)
-}
-
-fn parse_rhs_condition(input: ParseStream, lhs: Condition) -> Result<Condition> {
-    if let Ok(ident) = input.parse::<Ident>() {
-        match ident.to_string().as_str() {
-            "AND" => {
-                let right = Box::new(parse_condition_inner(input)?);
-                Ok(Condition::And(Box::new(lhs), right))
-            }
-            "OR" => {
-                let right = Box::new(parse_condition_inner(input)?);
-                Ok(Condition::Or(Box::new(lhs), right))
-            }
-            _ => Err(syn::Error::new(input.span(), "expected one of: AND, OR")),
-        }
-    } else {
-        Ok(lhs)
-    }
-}
-
-enum Value {
-    Int(syn::LitInt),
-    Str(syn::LitStr),
-    Bool(syn::LitBool),
-}
-
-impl Parse for Value {
-    fn parse(input: ParseStream) -> Result<Self> {
-        if input.peek(LitInt) {
-            return Ok(Value::Int(input.parse()?));
-        }
-        if input.peek(LitStr) {
-            return Ok(Value::Str(input.parse()?));
-        }
-        if input.peek(LitBool) {
-            return Ok(Value::Bool(input.parse()?));
-        }
-        Err(syn::Error::new(
-            input.span(),
-            format!("expected one of: {VALID_INT_TYPES_TEXT}"),
-        ))
-    }
-}
-
-impl Value {
-    fn expand(&self) -> TokenStream2 {
-        match self {
-            Value::Bool(value) => {
-                quote! {
-                    ::gate0::Value::Bool(#value)
-                }
-            }
-            Value::Str(value) => {
-                quote! {
-                    ::gate0::Value::String(#value)
-                }
-            }
-            Value::Int(value) => {
-                quote! {
-                    ::gate0::Value::Int(#value.into())
-                }
-            }
-        }
-    }
-}
-
-fn parse_atom(input: ParseStream) -> Result<Condition> {
-    if input.peek(Paren) {
-        let content;
-        syn::parenthesized!(content in input);
-        return parse_condition_inner(&content);
-    }
-
-    if input.peek(LitBool) {
-        let lit_bool: LitBool = input.parse()?;
-        return if lit_bool.value {
-            Ok(Condition::True)
-        } else {
-            Ok(Condition::False)
-        };
-    }
-
-    Err(syn::Error::new(
-        input.span(),
-        "expected true, false, or a parenthesized condition",
-    ))
-}
-
-fn parse_attr(input: ParseStream) -> Option<String> {
-    if input.peek(LitStr) {
-        let lit_str: LitStr = input.parse().ok()?;
-        return Some(lit_str.value());
-    }
-    if input.peek(Ident) {
-        let lit_str: Ident = input.parse().ok()?;
-        return Some(lit_str.to_string());
-    }
-    None
-}
-
-impl Condition {
-    fn expand(&self) -> TokenStream2 {
-        match self {
-            Condition::Equals { attr, value } => {
-                let attr_str = attr.clone();
-                let value = value.expand();
-                quote! {
-                    ::gate0::Condition::Equals {
-                        attr: #attr_str,
-                        value: #value,
-                    }
-                }
-            }
-            Condition::NotEquals { attr, value } => {
-                let attr_str = attr.clone();
-                match value {
-                    Value::Bool(value) => {
-                        quote! {
-                            ::gate0::Condition::Equals {
-                                attr: #attr_str,
-                                value: ::gate0::Value::Bool(#value),
-                            }
-                        }
-                    }
-                    Value::Str(value) => {
-                        quote! {
-                            ::gate0::Condition::Equals {
-                                attr: #attr_str,
-                                value: ::gate0::Value::String(#value),
-                            }
-                        }
-                    }
-                    Value::Int(value) => {
-                        quote! {
-                            ::gate0::Condition::Equals {
-                                attr: #attr_str,
-                                value: ::gate0::Value::Int(#value.into()),
-                            }
-                        }
-                    }
-                }
-            }
-            Condition::And(left, right) => {
-                let left_expr = left.expand();
-                let right_expr = right.expand();
-                quote! {
-                    ::gate0::Condition::And(
-                        Box::new(#left_expr),
-                        Box::new(#right_expr)
-                    )
-                }
-            }
-            Condition::Or(left, right) => {
-                let left_expr = left.expand();
-                let right_expr = right.expand();
-                quote! {
-                    ::gate0::Condition::Or(
-                        Box::new(#left_expr),
-                        Box::new(#right_expr)
-                    )
-                }
-            }
-            Condition::Not(inner) => {
-                let inner_expr = inner.expand();
-                quote! {
-                    ::gate0::Condition::Not(Box::new(#inner_expr))
-                }
-            }
-            Condition::True => quote! { ::gate0::Condition::True },
-            Condition::False => quote! { ::gate0::Condition::False },
-        }
-    }
+        .map_or(Ok(()), Err)
 }
@@ -1,6 +1,9 @@
-use gate0::{Policy, PolicyConfig, ReasonCode};
+use gate0::{Policy, PolicyConfig, ReasonCode, Request};
 
-use gate0_dsl::policy_builder;
+use gate0_dsl::{
+    assert_decision, ctx, decision_matrix, include_policy, policy_builder, policy_catalog,
+    policy_coverage, policy_doc, policy_explain, policy_fn, policy_tests,
+};
 use insta::assert_debug_snapshot;
 
 #[test]
@@ -150,3 +153,601 @@ fn test_external_builder() {
     .unwrap();
     assert_debug_snapshot!(policy);
 }
+
+#[test]
+fn test_expect() {
+    const ADMIN_ACCESS: ReasonCode = ReasonCode(100);
+    const MEMBER_READ: ReasonCode = ReasonCode(101);
+    let policy = policy_builder![
+        USE policy_builder![ALLOW ("auditor" "read" *) => 102;];
+
+        ALLOW ANY
+            WHERE { role EQ "admin" } => ADMIN_ACCESS;
+        ALLOW { action: ["read", "list"] }
+            WHERE { role EQ "member" } => MEMBER_READ;
+
+        EXPECT ("alice" "read" "doc1") WITH { role => "admin" } => ALLOW ADMIN_ACCESS;
+        EXPECT ("bob" "list" "doc1") WITH { "role" => "member" } => ALLOW(MEMBER_READ);
+        EXPECT ("bob" "update" "doc1") WITH { role => "member" } => DENY;
+        EXPECT ("eve" "read" "doc1") => DENY;
+        EXPECT ("auditor" "read" "doc1") => ALLOW 102;
+    ]
+    .build()
+    .unwrap();
+
+    assert_eq!(policy.rule_count(), 3);
+}
+
+#[test]
+#[should_panic(expected = r#"EXPECT ("bob" "update" "doc1") WITH { role => "member" } => ALLOW"#)]
+fn test_expect_mismatch() {
+    let _ = policy_builder![
+        ALLOW { action: "read" } WHERE { role EQ "member" } => 1;
+
+        EXPECT ("bob" "read" "doc1") WITH { role => "member" } => ALLOW 1;
+        EXPECT ("bob" "update" "doc1") WITH { role => "member" } => ALLOW;
+    ]
+    .build();
+}
+
+#[test]
+fn test_expect_self_test() {
+    let builder = policy_builder![
+        ALLOW { action: "read" } WHERE { role EQ "member" } => 1;
+
+        EXPECT ("bob" "read" "doc1") WITH { role => "member" } => ALLOW 1;
+        EXPECT ("bob" "update" "doc1") WITH { role => "member" } => ALLOW;
+    ];
+
+    let mismatches = builder.self_test().unwrap_err();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(
+        mismatches[0].expectation,
+        r#"EXPECT ("bob" "update" "doc1") WITH { role => "member" } => ALLOW"#
+    );
+    assert_eq!(mismatches[0].actual, "Deny ReasonCode(0)");
+}
+
+policy_fn! {
+    fn expect_policy;
+
+    USE policy_builder![ALLOW ("auditor" "read" *) => 102;];
+
+    ALLOW ANY
+        WHERE { role EQ "admin" } => ADMIN_ACCESS;
+
+    EXPECT ("alice" "read" "doc1") WITH { role => "admin" } => ALLOW ADMIN_ACCESS;
+    EXPECT ("auditor" "read" "doc1") => ALLOW 102;
+    EXPECT ("eve" "read" "doc1") => DENY;
+}
+
+const ADMIN_ACCESS: ReasonCode = ReasonCode(100);
+const MEMBER_READ: ReasonCode = ReasonCode(101);
+
+fn saas_policy() -> Policy {
+    policy_builder![
+        ALLOW ANY
+            WHERE { role EQ "admin" } => ADMIN_ACCESS;
+        ALLOW { action: ["read", "list"] }
+            WHERE { role EQ "member" } => MEMBER_READ;
+    ]
+    .build()
+    .unwrap()
+}
+
+#[test]
+fn test_include_policy() {
+    let policy = include_policy!("tests/policies/saas.gate0")
+        .build()
+        .unwrap();
+
+    assert_eq!(policy.rule_count(), 2);
+    assert_decision!(policy, "alice" "update" "doc-123" { role => "admin" } => ALLOW(ADMIN_ACCESS));
+    assert_decision!(policy, "bob" "update" "doc-123" { role => "member" } => DENY);
+    assert_decision!(policy, "bob" "list" "doc-123" { role => "member" } => ALLOW(MEMBER_READ));
+}
+
+mod table {
+    use super::*;
+
+    policy_tests! {
+        POLICY saas_policy();
+
+        "alice" "update" "doc-123" { "role" => "admin" } => ALLOW(ADMIN_ACCESS);
+        "bob" "read" "doc-123" { "role" => "member" } => ALLOW(MEMBER_READ);
+        "bob" "update" "doc-123" { "role" => "member" } => DENY;
+        "bob" "update" "doc-123" { "role" => "member" } => DENY;
+        stranger_is_denied: "eve" "read" "doc-123" => DENY;
+    }
+
+    #[test]
+    fn generated_names() {
+        bob_update_doc_123_role_member();
+        bob_update_doc_123_role_member_2();
+        stranger_is_denied();
+    }
+}
+
+#[test]
+fn test_assert_decision() {
+    let policy = saas_policy();
+    assert_decision!(policy, "alice" "update" "doc-123" { "role" => "admin" } => ALLOW(ADMIN_ACCESS));
+    assert_decision!(&policy, "bob" "list" "doc-123" { role => "member" } => ALLOW(MEMBER_READ));
+    assert_decision!(policy, "bob" "update" "doc-123" { "role" => "member" } => DENY);
+}
+
+#[test]
+#[should_panic(expected = r#"decision mismatch
+  request:  ("bob" "update" "doc-123")
+  context:  { role => "member" }
+  expected: Allow
+  actual:   Deny ReasonCode(3)
+  rules with matching targets:
+    ALLOW ANY WHERE { role EQ "admin" } => 1;
+    DENY ("bob" * ["doc-123", "doc-456"]) WHERE { NOT (suspended EQ true) AND role NEQ "owner" } => 3;"#)]
+fn test_assert_decision_mismatch() {
+    assert_decision!(
+        policy_builder![
+            ALLOW ANY WHERE { role EQ "admin" } => 1;
+            ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => 2;
+            DENY ("bob" * ["doc-123", "doc-456"])
+                WHERE { NOT (suspended EQ true) AND role NEQ "owner" } => 3;
+        ]
+        .build()
+        .unwrap(),
+        "bob" "update" "doc-123" { "role" => "member" } => ALLOW
+    );
+}
+
+#[test]
+#[should_panic(expected = r#"decision mismatch
+  request:  ("bob" "list" "doc-123")
+  context:  { role => "guest" }
+  expected: Allow
+  actual:   Deny ReasonCode(0)
+  rules with matching targets:
+    Rule { effect: Allow, target: Target { principal: Any, action: Any, resource: Any }, condition: Some(Equals { attr: "role", value: String("admin") }), reason: ReasonCode(100) }
+    Rule { effect: Allow, target: Target { principal: Any, action: OneOf(["read", "list"]), resource: Any }, condition: Some(Equals { attr: "role", value: String("member") }), reason: ReasonCode(101) }"#)]
+fn test_assert_decision_mismatch_variable() {
+    let policy = saas_policy();
+    assert_decision!(policy, "bob" "list" "doc-123" { "role" => "guest" } => ALLOW);
+}
+
+#[test]
+fn test_decision_matrix() {
+    insta::assert_snapshot!(decision_matrix![
+        ALLOW ANY
+            WHERE { role EQ "admin" } => ADMIN_ACCESS;
+        ALLOW { action: ["read", "list"] }
+            WHERE { role EQ "member" } => MEMBER_READ;
+        DENY ("eve" * *)
+            WHERE { NOT (verified EQ true) } => 3;
+    ]);
+}
+
+policy_coverage! {
+    static SAAS_COVERAGE: SaasCoverage;
+
+    ALLOW ANY WHERE { role EQ "admin" } => ADMIN_ACCESS;
+    ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => MEMBER_READ;
+    DENY ("eve" * *) => 3;
+    #[cfg(not(test))]
+    ALLOW ("alice" * *) => 4;
+}
+
+#[test]
+fn test_policy_coverage() {
+    let context = ctx! { "role" => "member" };
+    let decision = SAAS_COVERAGE
+        .evaluate(&Request::with_context("bob", "read", "doc", context))
+        .unwrap();
+    assert!(decision.is_allow());
+    assert_eq!(decision.reason, MEMBER_READ);
+    SAAS_COVERAGE.record(&Request::with_context("eve", "list", "doc", context));
+    let decision = SAAS_COVERAGE
+        .evaluate(&Request::with_context("bob", "update", "doc", context))
+        .unwrap();
+    assert!(!decision.is_allow());
+
+    let policy = SaasCoverage::builder().build().unwrap();
+    assert_eq!(policy.rule_count(), 3);
+    assert_eq!(SAAS_COVERAGE.policy().rule_count(), 3);
+
+    assert_eq!(SAAS_COVERAGE.matches(0), 0);
+    assert_eq!(SAAS_COVERAGE.matches(1), 2);
+    assert_eq!(SAAS_COVERAGE.matches(2), 1);
+    assert_eq!(SAAS_COVERAGE.uncovered(), vec![0]);
+    assert!(!SAAS_COVERAGE.enabled(3));
+    assert_eq!(SAAS_COVERAGE.matches_by_reason()["MEMBER_READ"], 2);
+
+    let (file, line, reason, text) = SaasCoverage::RULES[0];
+    assert_eq!(file, file!());
+    assert_eq!(reason, "ADMIN_ACCESS");
+    assert_eq!(
+        text,
+        r#"ALLOW ANY WHERE { role EQ "admin" } => ADMIN_ACCESS;"#
+    );
+    assert_eq!(SaasCoverage::RULES[2].1, line + 2);
+    assert!(
+        SAAS_COVERAGE
+            .report()
+            .starts_with("rule coverage: 2 of 3 rules matched")
+    );
+}
+
+#[test]
+fn test_policy_explain() {
+    const MEMBER_READ: ReasonCode = ReasonCode(2);
+    let line = line!();
+    policy_explain! {
+        fn explain;
+
+        ALLOW ANY WHERE { role EQ "admin" } => 1;
+        ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => MEMBER_READ;
+        DENY ("bob" * "doc-123")
+            WHERE { NOT (suspended EQ true) AND role EQ "member" } => 3;
+    }
+
+    let mut explanation = explain(&Request::with_context(
+        "bob",
+        "read",
+        "doc-456",
+        ctx! { "role" => "member" },
+    ));
+    assert!(explanation.decision.as_ref().unwrap().is_allow());
+    assert_eq!(
+        explanation
+            .rules
+            .iter()
+            .map(|rule| rule.applies)
+            .collect::<Vec<_>>(),
+        [false, true, false]
+    );
+    // Keeps the snapshot independent of where the test is in this file.
+    for rule in &mut explanation.rules {
+        rule.line -= line;
+    }
+    insta::assert_snapshot!(explanation.to_string());
+}
+
+#[test]
+fn test_policy_doc() {
+    const POLICY_DOC: &str = policy_doc! {
+        /// Admins can do anything.
+        ALLOW ANY WHERE { role EQ "admin" } => ADMIN_ACCESS;
+        /// Members can read and list,
+        /// but nothing else.
+        ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => MEMBER_READ;
+        DENY ("eve" "a|b" *)
+            WHERE { NOT (verified EQ true) AND (region EQ "eu" OR region NEQ "us") } => 3;
+        #[cfg(feature = "debug-admin")]
+        ALLOW ("alice" * *) => 4;
+    };
+    insta::assert_snapshot!(POLICY_DOC);
+}
+
+policy_catalog! {
+    static SAAS_RULES;
+
+    /// Admins can do anything.
+    #[id = "admin-all"]
+    #[owner = "team-platform"]
+    ALLOW ANY WHERE { role EQ "admin" } => ADMIN_ACCESS;
+    #[id = "member-read"]
+    #[ticket = "SEC-42"]
+    ALLOW { action: ["read", "list"] } WHERE { role EQ "member" } => MEMBER_READ;
+    DENY ("eve" * *) => 3;
+    #[cfg(not(test))]
+    #[id = "debug-admin"]
+    ALLOW ("alice" * *) => 4;
+}
+
+#[test]
+fn test_policy_catalog() {
+    use gate0_dsl_core::meta;
+
+    assert_eq!(SAAS_RULES.len(), 4);
+    let admin = &SAAS_RULES[0];
+    assert_eq!(admin.index, 0);
+    assert_eq!(admin.id(), Some("admin-all"));
+    assert_eq!(admin.owner(), Some("team-platform"));
+    assert_eq!(admin.doc, "Admins can do anything.");
+    assert_eq!(admin.reason, "ADMIN_ACCESS");
+    assert_eq!(
+        admin.rule,
+        r#"ALLOW ANY WHERE { role EQ "admin" } => ADMIN_ACCESS;"#
+    );
+
+    let member = meta::find(SAAS_RULES, "member-read").unwrap();
+    assert_eq!(member.index, 1);
+    assert_eq!(member.line, admin.line + 3);
+    assert_eq!(member.owner(), None);
+    assert_eq!(member.attribute("ticket"), Some("SEC-42"));
+    assert_eq!(member.doc, "");
+
+    assert_eq!(SAAS_RULES[2].attributes, &[]);
+    assert!(SAAS_RULES[2].enabled);
+    assert!(!meta::find(SAAS_RULES, "debug-admin").unwrap().enabled);
+    assert_eq!(meta::find(SAAS_RULES, "missing"), None);
+}
+
+#[test]
+fn test_policy_builder_catalog() {
+    let builder = policy_builder![
+        /// Admins can do anything.
+        #[id = "admin-all"]
+        ALLOW ANY WHERE { role EQ "admin" } => ADMIN_ACCESS;
+        ALLOW { action: "read" } => MEMBER_READ;
+    ];
+    let catalog = builder.catalog();
+    assert_eq!(catalog.len(), 2);
+    assert_eq!(catalog[0].id(), Some("admin-all"));
+    assert_eq!(catalog[0].doc, "Admins can do anything.");
+    assert_eq!(
+        catalog[1].rule,
+        r#"ALLOW { action: "read" } => MEMBER_READ;"#
+    );
+
+    let policy = builder.build().unwrap();
+    assert_decision!(policy, "bob" "read" "doc" => ALLOW(MEMBER_READ));
+}
+
+policy_fn! {
+    fn shared_policy;
+    CATALOG static SHARED_RULES;
+    DOC const SHARED_DOC;
+    COVERAGE static SHARED_COVERAGE: SharedCoverage;
+    EXPLAIN fn explain_shared;
+
+    /// Admins can do anything.
+    #[id = "admin-all"]
+    ALLOW ANY WHERE { role EQ "admin" } => ADMIN_ACCESS;
+    #[id = "eve-denied"]
+    DENY ("eve" * *) => 3;
+}
+
+#[test]
+fn test_policy_fn_companions() {
+    assert_eq!(SHARED_RULES.len(), 2);
+    assert_eq!(SHARED_RULES[1].id(), Some("eve-denied"));
+    assert!(SHARED_DOC.contains("| 1 | ALLOW | any | any | any | role is \"admin\" |"));
+
+    let context = ctx! { "role" => "admin" };
+    let request = Request::with_context("eve", "read", "doc", context);
+    let decision = SHARED_COVERAGE.evaluate(&request).unwrap();
+    assert_eq!(decision.reason, ReasonCode(3));
+    assert_eq!(SHARED_COVERAGE.uncovered(), Vec::<usize>::new());
+    assert_eq!(SharedCoverage::builder().build().unwrap().rule_count(), 2);
+
+    for _ in 0..2 {
+        let explanation = explain_shared(&request);
+        assert_eq!(explanation.decision.unwrap().reason, ReasonCode(3));
+        assert!(explanation.rules.iter().all(|rule| rule.applies));
+    }
+    assert_eq!(
+        shared_policy().build().unwrap().rule_count(),
+        SHARED_COVERAGE.policy().rule_count()
+    );
+}
+
+#[test]
+fn test_cfg_rules() {
+    let policy = policy_builder![
+        CONFIG { max_rules: 2 };
+
+        ALLOW ("alice" * *) => 1;
+        #[cfg(test)]
+        ALLOW ("bob" * *) => 2;
+        #[cfg(not(test))]
+        {
+            ALLOW ("eve" * *) => 3;
+            #[cfg(unix)]
+            ALLOW ("mallory" * *) => 4;
+        }
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "read" "doc" => ALLOW(2));
+    assert_decision!(policy, "eve" "read" "doc" => DENY);
+}
+
+#[test]
+fn test_for_blocks() {
+    let policy = policy_builder![
+        FOR { action: ["read", "list"] } {
+            ALLOW WHERE { role EQ "member" } => 1;
+            DENY WHERE { suspended EQ true } => 2;
+            ALLOW { principal: "alice", action: "list" } => 3;
+        }
+        DENY { action: "delete" } => 4;
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "read" "doc" { role => "member" } => ALLOW(1));
+    assert_decision!(policy, "bob" "write" "doc" { role => "member" } => DENY);
+    assert_decision!(policy, "alice" "list" "doc" { suspended => true } => DENY(2));
+    assert_decision!(policy, "alice" "list" "doc" => ALLOW(3));
+    assert_decision!(policy, "alice" "read" "doc" => DENY);
+}
+
+#[test]
+fn test_with_blocks() {
+    let policy = policy_builder![
+        WITH { tenant_tier EQ "enterprise" } {
+            ALLOW { action: "export" } => 1;
+            WITH { role EQ "admin" } {
+                ALLOW { action: "delete" } => 2;
+            }
+        }
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "export" "doc" { tenant_tier => "enterprise" } => ALLOW(1));
+    assert_decision!(policy, "bob" "export" "doc" { tenant_tier => "free" } => DENY);
+    assert_decision!(policy, "bob" "delete" "doc" { tenant_tier => "enterprise" } => DENY);
+    assert_decision!(policy, "bob" "delete" "doc" { tenant_tier => "enterprise", role => "admin" } => ALLOW(2));
+    assert_decision!(policy, "bob" "delete" "doc" { role => "admin" } => DENY);
+}
+
+policy_fn! {
+    fn tenant_policy;
+    PARAMS(tenant: &'static str, min_level: i64);
+
+    ALLOW { action: "read" } WHERE { tenant EQ $tenant } => 1;
+    ALLOW ANY WHERE { tenant EQ $tenant AND level EQ $min_level } => 2;
+}
+
+#[test]
+fn test_policy_fn() {
+    let acme = tenant_policy("acme", 3).build().unwrap();
+    insta::assert_debug_snapshot!(acme);
+    assert_decision!(acme, "bob" "write" "doc" { tenant => "acme", level => 3 } => ALLOW(2));
+    assert_decision!(acme, "bob" "write" "doc" { tenant => "acme", level => 2 } => DENY);
+
+    let globex = tenant_policy("globex", 1).build().unwrap();
+    assert_decision!(globex, "bob" "read" "doc" { tenant => "globex" } => ALLOW(1));
+    assert_decision!(globex, "bob" "read" "doc" { tenant => "acme" } => DENY);
+}
+
+#[test]
+fn test_for_loops() {
+    const REGION_OK: ReasonCode = ReasonCode(7);
+    let policy = policy_builder![
+        FOR region IN ["eu", "us", "apac"] {
+            ALLOW ("svc-$region" ANY ANY) WHERE { region EQ $region } => REGION_OK;
+        }
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "svc-eu" "read" "doc" { region => "eu" } => ALLOW(REGION_OK));
+    assert_decision!(policy, "svc-eu" "read" "doc" { region => "us" } => DENY);
+    assert_decision!(policy, "svc-apac" "read" "doc" { region => "apac" } => ALLOW(REGION_OK));
+}
+
+#[test]
+fn test_role_hierarchy() {
+    let policy = policy_builder![
+        ROLES { owner > admin > member > viewer };
+
+        ALLOW { action: "read" } WHERE { role AT_LEAST "viewer" } => 1;
+        ALLOW { action: "write" } WHERE { role AT_LEAST "member" } => 2;
+        ALLOW { action: "delete" } WHERE { role AT_LEAST "admin" } => 3;
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "read" "doc" { role => "viewer" } => ALLOW(1));
+    assert_decision!(policy, "bob" "write" "doc" { role => "viewer" } => DENY);
+    assert_decision!(policy, "bob" "write" "doc" { role => "owner" } => ALLOW(2));
+    assert_decision!(policy, "bob" "delete" "doc" { role => "member" } => DENY);
+    assert_decision!(policy, "bob" "delete" "doc" { role => "admin" } => ALLOW(3));
+    assert_decision!(policy, "bob" "delete" "doc" { role => "owner" } => ALLOW(3));
+}
+
+#[test]
+fn test_rbac_tables() {
+    const AUDIT_READ: ReasonCode = ReasonCode(10);
+    let policy = policy_builder![
+        RBAC {
+            attr: role;
+            reason: 1;
+            admin => *;
+            member => ["read", "list"];
+            auditor => ["read"] => AUDIT_READ;
+        }
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "delete" "doc" { role => "admin" } => ALLOW(1));
+    assert_decision!(policy, "bob" "list" "doc" { role => "member" } => ALLOW(1));
+    assert_decision!(policy, "bob" "delete" "doc" { role => "member" } => DENY);
+    assert_decision!(policy, "bob" "read" "doc" { role => "auditor" } => ALLOW(AUDIT_READ));
+    assert_decision!(policy, "bob" "list" "doc" { role => "auditor" } => DENY);
+}
+
+#[test]
+fn test_decision_tables() {
+    let policy = policy_builder![
+        TABLE COMPLETE (role: ["admin", "member"], region: ["eu", "us"], action) {
+            ("admin", *, *) => ALLOW 1;
+            ("member", "eu", ["read", "list"]) => ALLOW 2;
+            ("member", "us", "read") => ALLOW 3;
+            ("member", "us", "delete") => DENY 4;
+        }
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "delete" "doc" { role => "admin", region => "us" } => ALLOW(1));
+    assert_decision!(policy, "bob" "list" "doc" { role => "member", region => "eu" } => ALLOW(2));
+    assert_decision!(policy, "bob" "list" "doc" { role => "member", region => "us" } => DENY);
+    assert_decision!(policy, "bob" "read" "doc" { role => "member", region => "us" } => ALLOW(3));
+    assert_decision!(policy, "bob" "delete" "doc" { role => "member", region => "us" } => DENY(4));
+}
+
+#[test]
+fn test_else_chains() {
+    let policy = policy_builder![
+        ALLOW { action: "export" } WHERE { plan EQ "premium" } => 1
+            ELSE IF { role EQ "admin" } ALLOW => 2
+            ELSE DENY => 3;
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "export" "doc" { plan => "premium", role => "admin" } => ALLOW(1));
+    assert_decision!(policy, "bob" "export" "doc" { plan => "free", role => "admin" } => ALLOW(2));
+    assert_decision!(policy, "bob" "export" "doc" { plan => "free", role => "member" } => DENY(3));
+    assert_decision!(policy, "bob" "read" "doc" { plan => "free" } => DENY);
+}
+
+#[test]
+fn test_match_blocks() {
+    let policy = policy_builder![
+        MATCH role {
+            "admin" => ALLOW ANY => 1;
+            "member" => ALLOW { action: ["read"] } => 2;
+            _ => DENY ANY => 3;
+        }
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "delete" "doc" { role => "admin" } => ALLOW(1));
+    assert_decision!(policy, "bob" "read" "doc" { role => "member" } => ALLOW(2));
+    assert_decision!(policy, "bob" "delete" "doc" { role => "member" } => DENY);
+    assert_decision!(policy, "bob" "read" "doc" { role => "guest" } => DENY(3));
+}
+
+#[test]
+fn test_combine_first_match() {
+    let policy = policy_builder![
+        COMBINE FIRST_MATCH;
+
+        ALLOW ("bob" ["read", "write"] *) => 1;
+        DENY (["bob", "eve"] "write" *) => 2;
+        ALLOW (["bob", "eve"] ["read", "write"] *) WHERE { role EQ "admin" } => 3;
+    ]
+    .build()
+    .unwrap();
+
+    insta::assert_debug_snapshot!(policy);
+    assert_decision!(policy, "bob" "write" "doc" => ALLOW(1));
+    assert_decision!(policy, "eve" "write" "doc" { role => "admin" } => DENY(2));
+    assert_decision!(policy, "eve" "read" "doc" { role => "admin" } => ALLOW(3));
+    assert_decision!(policy, "eve" "read" "doc" => DENY);
+}
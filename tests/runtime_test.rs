@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use gate0::ReasonCode;
+use gate0_dsl_core::runtime::{ParseError, parse_policy};
+
+fn reasons() -> HashMap<&'static str, ReasonCode> {
+    HashMap::from([
+        ("REASON_ONE", ReasonCode(1)),
+        ("REASON_TWO", ReasonCode(2)),
+        ("REASON_THREE", ReasonCode(3)),
+    ])
+}
+
+/// Compares a loaded policy against the snapshot the macro test of the same name
+/// recorded in `basic_test.rs`.
+fn assert_same_as_macro(snapshot: &str, source: &str) {
+    let policy = parse_policy(source, &reasons()).unwrap().build().unwrap();
+    insta::with_settings!({ prepend_module_to_snapshot => false }, {
+        insta::assert_debug_snapshot!(format!("basic_test__{snapshot}"), policy);
+    });
+}
+
+#[test]
+fn test_simple_any_rules() {
+    assert_same_as_macro(
+        "simple_any_rules",
+        r#"
+        // comment test
+        ALLOW *   => 1;
+        ALLOW *   => REASON_ONE;
+
+        ALLOW ANY => 2;
+        ALLOW ANY => REASON_TWO;
+
+        DENY  *   => 3;
+        DENY  ANY => REASON_THREE;
+        "#,
+    );
+}
+
+#[test]
+fn test_simple_tuple_rules() {
+    assert_same_as_macro(
+        "simple_tuple_rules",
+        r#"
+        ALLOW ("alice" "read"  "doc1") => 1;
+        ALLOW ("bob"   "write" "doc2") => REASON_ONE;
+
+        DENY  ("eve" * ANY) => 2;
+        DENY  (["eve", "carl"] "write" *) => 2;
+        DENY  ("mallory" "delete" "doc3") => REASON_TWO;
+        "#,
+    );
+}
+
+#[test]
+fn test_field_rules() {
+    assert_same_as_macro(
+        "field_rules",
+        r#"
+        ALLOW {
+            principal: "alice",
+            action:    "read",
+            resource:  "doc1",
+        } => 1;
+
+        ALLOW {
+            principal: ["bob", "carl"],
+            action:    ["write", "update"],
+            resource:  ANY,
+        } => REASON_ONE;
+
+        DENY {
+            principal: "eve",
+            action:    *,
+            resource:  ANY,
+        } => 2;
+
+        DENY {
+            principal: ["mallory", "trent"],
+            action:    "delete",
+            resource:  ["doc2", "doc3"],
+        } => REASON_TWO;
+        "#,
+    );
+}
+
+#[test]
+fn test_mixed_rules() {
+    assert_same_as_macro(
+        "mixed_rules",
+        r#"
+        ALLOW * => 1;
+        ALLOW ("alice" "read" "doc1") => REASON_TWO;
+        DENY {
+            principal: "eve",
+            action:    *,
+            resource:  ANY,
+        } => REASON_THREE;
+        "#,
+    );
+}
+
+#[test]
+fn test_where() {
+    assert_same_as_macro(
+        "where",
+        r#"
+        ALLOW ANY
+            WHERE { role EQ "admin" } => 1;
+
+        ALLOW ANY
+            WHERE { "role" NEQ "admin" } => 1;
+
+        ALLOW ANY
+            WHERE { NOT (role EQ "admin" OR true) } => 1;
+
+        ALLOW ANY
+            WHERE { NOT ((role NEQ "admin") AND true) } => 2;
+
+        // same as (NOT true) AND true
+        ALLOW ANY
+            WHERE { NOT true AND true } => 1;
+        "#,
+    );
+}
+
+#[test]
+fn test_config() {
+    let policy = parse_policy(
+        "CONFIG { max_rules: 500, max_condition_depth: 5 };",
+        &reasons(),
+    )
+    .unwrap()
+    .build()
+    .unwrap();
+
+    assert_eq!(policy.config().max_rules, 500);
+    assert_eq!(policy.config().max_condition_depth, 5);
+}
+
+#[test]
+fn test_errors() {
+    let error = |source: &str| {
+        let ParseError {
+            line,
+            column,
+            message,
+            ..
+        } = parse_policy(source, &reasons()).err().unwrap();
+        (line, column, message)
+    };
+
+    assert_eq!(
+        error("ALLOW ANY => 1;\n  ALLOW ANY => ADMIN;"),
+        (2, 16, String::from("unknown reason code `ADMIN`"))
+    );
+    assert_eq!(
+        error("ALLOW ANY => 1;\nALLOW NONE => 2;"),
+        (2, 7, String::from("expected ANY, *, tuple, or struct"))
+    );
+    assert_eq!(
+        error("ALLOW ANY => 1;\nALLOW ANY => 2"),
+        (2, 15, String::from("expected `;`"))
+    );
+    assert_eq!(
+        error("CONFIG { max_rules: LIMIT };"),
+        (1, 21, String::from("expected an integer literal"))
+    );
+    assert_eq!(
+        error("USE builder;"),
+        (1, 5, String::from("USE isn't supported at runtime"))
+    );
+    assert_eq!(
+        error("ALLOW ANY => 1;\n#[cfg(test)] { DENY ANY => 2; }"),
+        (2, 16, String::from("#[cfg] isn't supported at runtime"))
+    );
+}
+
+/// The decision of the first rule in `rules` that matches `request`, evaluating them one
+/// at a time, or the one of a policy without rules when none does.
+fn first_match(rules: &[&str], request: &gate0::Request) -> (bool, ReasonCode) {
+    let evaluate = |source: &str| {
+        let policy = parse_policy(source, &reasons()).unwrap().build().unwrap();
+        let decision = policy.evaluate(request).unwrap();
+        (decision.is_allow(), decision.reason)
+    };
+    let no_match = evaluate("");
+    rules
+        .iter()
+        .map(|rule| evaluate(rule))
+        .find(|decision| *decision != no_match)
+        .unwrap_or(no_match)
+}
+
+#[test]
+fn test_combine_first_match() {
+    assert_first_match(&[
+        r#"DENY ("eve" * *) WHERE { suspended EQ true } => 1;"#,
+        r#"ALLOW (["eve", "bob"] ["read", "write"] *) WHERE { role EQ "member" } => 2;"#,
+        r#"DENY (["eve", "bob", "alice"] "write" "log") => 3;"#,
+        r#"ALLOW (["eve", "bob", "alice"] ["read", "write", "delete"] ["doc", "log"])
+            WHERE { role EQ "admin" } => 4;"#,
+        r#"DENY (["bob", "alice"] "delete" ["doc", "log"]) => 5;"#,
+        r#"ALLOW ("alice" ["read", "delete"] "doc") => 6;"#,
+    ]);
+}
+
+#[test]
+fn test_combine_first_match_catch_all() {
+    assert_first_match(&[
+        r#"DENY ("eve" * *) WHERE { suspended EQ true } => 1;"#,
+        r#"DENY (["bob", "alice"] "delete" *) => 5;"#,
+        r#"ALLOW ("alice" ["read", "delete"] "doc") => 6;"#,
+        r#"ALLOW ANY => 2;"#,
+    ]);
+    assert_first_match(&[
+        r#"DENY ("eve" * *) WHERE { suspended EQ true } => 1;"#,
+        r#"ALLOW ANY WHERE { role EQ "admin" } => 4;"#,
+        r#"DENY (["bob", "alice"] "delete" *) => 5;"#,
+        r#"ALLOW ANY WHERE { role EQ "member" } => 2;"#,
+        r#"DENY ANY => 3;"#,
+    ]);
+    // The last DENY is dropped, leaving the requests it decided to gate0's default.
+    assert_first_match_of(
+        &[r#"ALLOW ("alice" "read" *) => 1;"#, r#"DENY ANY => 2;"#],
+        &[r#"ALLOW ("alice" "read" *) => 1;"#],
+    );
+    assert_first_match_of(
+        &[
+            r#"DENY ("eve" * *) WHERE { suspended EQ true } => 1;"#,
+            r#"ALLOW (["eve", "bob"] * "doc") WHERE { role EQ "member" } => 2;"#,
+            r#"DENY (* "delete" *) => 3;"#,
+        ],
+        &[
+            r#"DENY ("eve" * *) WHERE { suspended EQ true } => 1;"#,
+            r#"ALLOW (["eve", "bob"] * "doc") WHERE { role EQ "member" } => 2;"#,
+        ],
+    );
+}
+
+/// Checks that `rules` with `COMBINE FIRST_MATCH;` decide every request in a grid the
+/// way the first matching rule does.
+fn assert_first_match(rules: &[&str]) {
+    assert_first_match_of(rules, rules);
+}
+
+/// Checks that `rules` with `COMBINE FIRST_MATCH;` decide every request of the grid like
+/// the first matching rule of `expected` does.
+fn assert_first_match_of(rules: &[&str], expected: &[&str]) {
+    let source = format!("COMBINE FIRST_MATCH;\n{}", rules.join("\n"));
+    let policy = parse_policy(&source, &reasons()).unwrap().build().unwrap();
+
+    let suspended = [None, Some(true), Some(false)];
+    let roles = [None, Some("member"), Some("admin")];
+    for principal in ["eve", "bob", "alice", "mallory"] {
+        for action in ["read", "write", "delete"] {
+            for resource in ["doc", "log"] {
+                for (suspended, role) in suspended
+                    .iter()
+                    .flat_map(|suspended| roles.iter().map(move |role| (suspended, role)))
+                {
+                    let mut context = Vec::new();
+                    if let Some(suspended) = suspended {
+                        context.push(("suspended", gate0::Value::Bool(*suspended)));
+                    }
+                    if let Some(role) = role {
+                        context.push(("role", gate0::Value::String(role)));
+                    }
+                    let request =
+                        gate0::Request::with_context(principal, action, resource, &context);
+                    let decision = policy.evaluate(&request).unwrap();
+                    assert_eq!(
+                        (decision.is_allow(), decision.reason),
+                        first_match(expected, &request),
+                        "{principal} {action} {resource} {context:?}"
+                    );
+                }
+            }
+        }
+    }
+}